@@ -8,19 +8,38 @@
 extern crate kdb_c_api;
 
 use kdb_c_api::*;
+use kdb_c_api::ipc::*;
+use std::collections::BTreeMap;
 use std::ffi::c_void;
-use libc::send;
+use std::os::unix::io::RawFd;
+use std::sync::Mutex;
+use std::time::Duration;
 
 //++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
 //                              Macros                                  //
 //++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
 
+// Entry point loading all the functions below.
+export_init!();
+
+/// Object wrapped in a q foreign object.
+struct Pilgrimage{
+  steps: i64
+}
+
+impl Drop for Pilgrimage{
+  fn drop(&mut self){
+    println!("The pilgrimage ended after {} steps.", self.steps);
+  }
+}
+
 /// Example of `KUNLL`.
 #[no_mangle]
 pub extern "C" fn vanity(_: K) -> K{
   println!("Initialized something, probably it is your mindset.");
   KNULL!()
 }
+export!(".capi", vanity, 1, "Example of `KNULL`.");
 
 /// Example of `str_to_S`.
 #[no_mangle]
@@ -29,6 +48,7 @@ pub extern "C" fn bigbang(_: K) -> K{
     ks(str_to_S!("super_illusion"))
   }
 }
+export!(".capi", bigbang, 1, "Example of `str_to_S`.");
 
 /// Example of `null_terminated_str_to_const_S`.
 #[no_mangle]
@@ -42,6 +62,45 @@ pub extern "C" fn must_be_int(obj: K) -> K{
     }
   }
 }
+export!(".capi", must_be_int, 1, "Example of `null_terminated_str_to_const_S`.");
+
+/// Order book whose methods are exported as a q namespace.
+#[derive(Default)]
+struct OrderBook{
+  bids: Vec<(f64, i64)>
+}
+
+/// Constructor of `OrderBook`.
+extern "C" fn orderbook_new(_: K) -> K{
+  new_foreign(OrderBook::default())
+}
+
+foreign_method!{
+  /// Add a bid to the order book.
+  fn orderbook_add(book: &mut OrderBook, price: K, size: K) -> K{
+    book.bids.push((price.get_float().unwrap_or(0.0), size.get_long().unwrap_or(0)));
+    KNULL!()
+  }
+}
+
+foreign_method!{
+  /// Get the best bid price.
+  fn orderbook_best(book: &mut OrderBook) -> K{
+    let best=book.bids.iter().map(|bid| bid.0).fold(f64::NAN, f64::max);
+    unsafe{kf(best)}
+  }
+}
+
+/// Example of `namespace` and `foreign_method`.
+#[no_mangle]
+pub extern "C" fn orderbook(_: K) -> K{
+  namespace!{
+    "new" => (orderbook_new, 1),
+    "add" => (orderbook_add, 3),
+    "best" => (orderbook_best, 1)
+  }
+}
+export!(".capi", orderbook, 1, "Example of `namespace` and `foreign_method`.");
 
 //++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
 //                             K Utility                                //
@@ -62,6 +121,7 @@ pub extern "C" fn modify_long_list_a_bit(long_list: K) -> K{
     } 
   }
 }
+export!(".capi", modify_long_list_a_bit, 1, "Example of `as_mut_slice`.");
 
 #[no_mangle]
 pub extern "C" fn print_byte(atom: K) -> K{
@@ -73,6 +133,7 @@ pub extern "C" fn print_byte(atom: K) -> K{
     Err(error) => unsafe{krr(null_terminated_str_to_const_S(error))}
   }
 }
+export!(".capi", print_byte, 1, "Example of `get_byte`.");
 
 #[no_mangle]
 pub extern "C" fn print_short(atom: K) -> K{
@@ -84,6 +145,7 @@ pub extern "C" fn print_short(atom: K) -> K{
     Err(error) => unsafe{krr(null_terminated_str_to_const_S(error))}
   }
 }
+export!(".capi", print_short, 1, "Example of `get_short`.");
 
 #[no_mangle]
 pub extern "C" fn print_int(atom: K) -> K{
@@ -95,6 +157,7 @@ pub extern "C" fn print_int(atom: K) -> K{
     Err(error) => unsafe{krr(null_terminated_str_to_const_S(error))}
   }
 }
+export!(".capi", print_int, 1, "Example of `get_int`.");
 
 #[no_mangle]
 pub extern "C" fn print_long(atom: K) -> K{
@@ -107,6 +170,7 @@ pub extern "C" fn print_long(atom: K) -> K{
   }
   
 }
+export!(".capi", print_long, 1, "Example of `get_long`.");
 
 #[no_mangle]
 pub extern "C" fn print_real(atom: K) -> K{
@@ -118,6 +182,7 @@ pub extern "C" fn print_real(atom: K) -> K{
     Err(error) => unsafe{krr(null_terminated_str_to_const_S(error))}
   }
 }
+export!(".capi", print_real, 1, "Example of `get_real`.");
 
 #[no_mangle]
 pub extern "C" fn print_float(atom: K) -> K{
@@ -129,6 +194,7 @@ pub extern "C" fn print_float(atom: K) -> K{
     Err(error) => unsafe{krr(null_terminated_str_to_const_S(error))}
   }
 }
+export!(".capi", print_float, 1, "Example of `get_float`.");
 
 #[no_mangle]
 pub extern "C" fn print_char(atom: K) -> K{
@@ -140,6 +206,7 @@ pub extern "C" fn print_char(atom: K) -> K{
     Err(error) => unsafe{krr(null_terminated_str_to_const_S(error))}
   }
 }
+export!(".capi", print_char, 1, "Example of `get_char`.");
 
 #[no_mangle]
 pub extern "C" fn print_symbol2(atom: K) -> K{
@@ -151,6 +218,7 @@ pub extern "C" fn print_symbol2(atom: K) -> K{
     Err(error) => unsafe{krr(null_terminated_str_to_const_S(error))}
   }
 }
+export!(".capi", print_symbol2, 1, "Example of `get_symbol`.");
 
 #[no_mangle]
 pub extern "C" fn print_string(string: K) -> K{
@@ -162,6 +230,20 @@ pub extern "C" fn print_string(string: K) -> K{
     Err(error) => unsafe{krr(null_terminated_str_to_const_S(error))}
   }
 }
+export!(".capi", print_string, 1, "Example of `get_string`.");
+
+/// Example of `as_foreign`.
+#[no_mangle]
+pub extern "C" fn walk(pilgrimage: K, steps: K) -> K{
+  match unsafe{pilgrimage.as_foreign::<Pilgrimage>()}{
+    Ok(pilgrimage) => {
+      pilgrimage.steps+=steps.get_long().unwrap_or(0);
+      unsafe{kj(pilgrimage.steps)}
+    },
+    Err(error) => unsafe{krr(null_terminated_str_to_const_S(error))}
+  }
+}
+export!(".capi", walk, 2, "Example of `as_foreign`.");
 
 //++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
 //                            Constructors                              //
@@ -172,60 +254,70 @@ pub extern "C" fn print_string(string: K) -> K{
 pub extern "C" fn create_bool(_: K) -> K{
   unsafe{kb(1)}
 }
+export!(".capi", create_bool, 1, "Example of `kb`.");
 
 /// Example of `ku`.
 #[no_mangle]
 pub extern "C" fn create_guid(_: K) -> K{
   unsafe{ku(U::new([0x1e_u8, 0x11, 0x17, 0x0c, 0x42, 0x24, 0x25, 0x2c, 0x1c, 0x14, 0x1e, 0x22, 0x4d, 0x3d, 0x46, 0x24]))}
 }
+export!(".capi", create_guid, 1, "Example of `ku`.");
 
 /// Example of `kg`.
 #[no_mangle]
 pub extern "C" fn create_byte(_: K) -> K{
   unsafe{kg(0x3c)}
 }
+export!(".capi", create_byte, 1, "Example of `kg`.");
 
 /// Example of `kh`.
 #[no_mangle]
 pub extern "C" fn create_short(_: K) -> K{
   unsafe{kh(-144)}
 }
+export!(".capi", create_short, 1, "Example of `kh`.");
 
 /// Example of `ki`.
 #[no_mangle]
 pub extern "C" fn create_int(_: K) -> K{
   unsafe{ki(86400000)}
 }
+export!(".capi", create_int, 1, "Example of `ki`.");
 
 /// Example of `kj`.
 #[no_mangle]
 pub extern "C" fn create_long(_: K) -> K{
   unsafe{kj(-668541276001729000)}
 }
+export!(".capi", create_long, 1, "Example of `kj`.");
 
 /// Example of `ke`.
 #[no_mangle]
 pub extern "C" fn create_real(_: K) -> K{
   unsafe{ke(0.00324)}
 }
+export!(".capi", create_real, 1, "Example of `ke`.");
 
 /// Example of `kf`.
 #[no_mangle]
 pub extern "C" fn create_float(_: K) -> K{
   unsafe{kf(-6302.620)}
 }
+export!(".capi", create_float, 1, "Example of `kf`.");
 
 /// Example of `kc`.
 #[no_mangle]
 pub extern "C" fn create_char(_: K) -> K{
   unsafe{kc('q' as I)}
 }
+export!(".capi", create_char, 1, "Example of `kc`.");
 
 /// Example of `ks`.
 #[no_mangle]
 pub extern "C" fn create_symbol(_: K) -> K{
   unsafe{ks(str_to_S!("symbolism"))}
 }
+export!(".capi", create_symbol, 1, "Example of `ks`.");
 
 /// Example of `ktj` for timestamp.
 #[no_mangle]
@@ -233,6 +325,7 @@ pub extern "C" fn create_timestamp(_: K) -> K{
   // 2015.03.16D00:00:00:00.000000000
   unsafe{ktj(-qtype::TIMESTAMP as I, 479779200000000000)}
 }
+export!(".capi", create_timestamp, 1, "Example of `ktj` for timestamp.");
 
 /// Example of `ktj` for timespan.
 #[no_mangle]
@@ -240,6 +333,7 @@ pub extern "C" fn create_timespan(_: K) -> K{
   // -1D01:30:00.001234567
   unsafe{ktj(-qtype::TIMESPAN as I, -91800001234567)}
 }
+export!(".capi", create_timespan, 1, "Example of `ktj` for timespan.");
 
 /// Example of `kd`.
 #[no_mangle]
@@ -247,6 +341,7 @@ pub extern "C" fn create_date(_: K) -> K{
   // 1999.12.25
   unsafe{kd(-7)}
 }
+export!(".capi", create_date, 1, "Example of `kd`.");
 
 /// Example of `kz`.
 #[no_mangle]
@@ -254,6 +349,7 @@ pub extern "C" fn create_datetime(_: K) -> K{
   // 2015.03.16T12:00:00:00.000
   unsafe{kz(5553.5)}
 }
+export!(".capi", create_datetime, 1, "Example of `kz`.");
 
 /// Example of `kt`.
 #[no_mangle]
@@ -261,18 +357,21 @@ pub extern "C" fn create_time(_: K) -> K{
   // -01:30:00.123
   unsafe{kt(-5400123)}
 }
+export!(".capi", create_time, 1, "Example of `kt`.");
 
 /// Example of `kp`.
 #[no_mangle]
 pub extern "C" fn create_string(_: K) -> K{
   unsafe{kp(str_to_S!("this is a text."))}
 }
+export!(".capi", create_string, 1, "Example of `kp`.");
 
 /// Example of `kpn`.
 #[no_mangle]
 pub extern "C" fn create_string2(_: K) -> K{
   unsafe{kpn(str_to_S!("The meeting was too long and I felt it s..."), 24)}
 }
+export!(".capi", create_string2, 1, "Example of `kpn`.");
 
 /// Example of `xT`.
 #[no_mangle]
@@ -292,12 +391,14 @@ pub extern "C" fn create_table(_: K) -> K{
     xT(xD(keys, values))
   }
 }
+export!(".capi", create_table, 1, "Example of `xT`.");
 
 /// Example of `ktd`.
 #[no_mangle]
 pub extern "C" fn keyed_to_simple_table(dummy: K) -> K{
   unsafe{ktd(create_keyed_table(dummy))}
 }
+export!(".capi", keyed_to_simple_table, 1, "Example of `ktd`.");
 
 /// Example of `xD`.
 #[no_mangle]
@@ -315,12 +416,14 @@ pub extern "C" fn create_dictionary(_: K) -> K{
     xD(keys, values)
   }
 }
+export!(".capi", create_dictionary, 1, "Example of `xD`.");
 
 /// Example of `knt`.
 #[no_mangle]
 pub extern "C" fn create_keyed_table(dummy: K) -> K{
   unsafe{knt(1, create_table(dummy))}
 }
+export!(".capi", create_keyed_table, 1, "Example of `knt`.");
 
 /// Example of `krr`.
 #[no_mangle]
@@ -329,6 +432,7 @@ pub extern "C" fn thai_kick(_: K) -> K{
     krr(null_terminated_str_to_const_S("Thai kick unconditionally!!\0"))
   }
 }
+export!(".capi", thai_kick, 1, "Example of `krr`.");
 
 /// Example of `jv`.
 #[no_mangle]
@@ -338,6 +442,7 @@ pub extern "C" fn concat_list(mut list1: K, list2: K) -> K{
     r1(list1)
   }
 }
+export!(".capi", concat_list, 2, "Example of `jv`.");
 
 /// Example of `js`.
 #[no_mangle]
@@ -351,6 +456,7 @@ pub extern "C" fn create_symbol_list(_: K) -> K{
     list
   }
 }
+export!(".capi", create_symbol_list, 1, "Example of `js`.");
 
 /// Example of `jk`.
 #[no_mangle]
@@ -363,6 +469,7 @@ pub extern "C" fn create_compound_list(_: K) -> K{
     list
   }
 }
+export!(".capi", create_compound_list, 1, "Example of `jk`.");
 
 /// Example of `ee`. 
 #[no_mangle]
@@ -380,6 +487,135 @@ pub extern "C" fn catchy(func: K, args: K) -> K{
     }
   }
 }
+export!(".capi", catchy, 2, "Example of `ee`.");
+
+/// Example of `apply`.
+#[no_mangle]
+pub extern "C" fn catchy2(func: K, arg1: K, arg2: K) -> K{
+  match unsafe{apply(func, &[KOwned::from_borrowed(arg1), KOwned::from_borrowed(arg2)])}{
+    Ok(result) => result.into_raw(),
+    Err(error) => {
+      println!("error: {}", error);
+      if let Some(backtrace)=error.backtrace(){
+        println!("{}", backtrace);
+      }
+      KNULL!()
+    }
+  }
+}
+export!(".capi", catchy2, 3, "Example of `apply`.");
+
+/// Example of `q!`.
+#[no_mangle]
+pub extern "C" fn drift(prices: K, days: K) -> K{
+  let drifted=|| -> Result<Vec<f64>, KError>{
+    let days: i64=q!("{2*x}", unsafe{KOwned::from_borrowed(days)})?;
+    q!("{x+y}", days, unsafe{Vec::<f64>::from_q(prices)}?)
+  };
+  match drifted(){
+    Ok(prices) => prices.into_q().into_raw(),
+    Err(error) => error.to_q()
+  }
+}
+export!(".capi", drift, 2, "Example of `q!`.");
+
+/// Example of `get_global`.
+#[no_mangle]
+pub extern "C" fn read_scroll(_: K) -> K{
+  match get_global::<String>(".scroll.verse"){
+    Ok(verse) => {
+      println!("{}", verse);
+      unsafe{kj(verse.len() as J)}
+    },
+    Err(error) => error.to_q()
+  }
+}
+export!(".capi", read_scroll, 1, "Example of `get_global`.");
+
+/// Example of `set_global`.
+#[no_mangle]
+pub extern "C" fn write_scroll(verse: K) -> K{
+  match set_global(".scroll.verse", unsafe{KOwned::from_borrowed(verse)}){
+    Ok(_) => KNULL!(),
+    Err(error) => error.to_q()
+  }
+}
+export!(".capi", write_scroll, 1, "Example of `set_global`.");
+
+/// Example of `upsert_global`.
+#[no_mangle]
+pub extern "C" fn record_census(records: K) -> K{
+  match upsert_global("census", unsafe{KOwned::from_borrowed(records)}){
+    Ok(_) => KNULL!(),
+    Err(error) => error.to_q()
+  }
+}
+export!(".capi", record_census, 1, "Example of `upsert_global`.");
+
+/// Example of `global_exists`.
+#[no_mangle]
+pub extern "C" fn is_written(name: K) -> K{
+  match name.get_symbol(){
+    Ok(name) => unsafe{kb(global_exists(name) as I)},
+    Err(error) => unsafe{krr(null_terminated_str_to_const_S(error))}
+  }
+}
+export!(".capi", is_written, 1, "Example of `global_exists`.");
+
+/// Example of `list_namespace`.
+#[no_mangle]
+pub extern "C" fn open_scroll(namespace: K) -> K{
+  let list=namespace.get_symbol().map_err(KError::from).and_then(list_namespace);
+  match list{
+    Ok(names) => names.into_iter().map(Symbol).collect::<Vec<_>>().into_q().into_raw(),
+    Err(error) => error.to_q()
+  }
+}
+export!(".capi", open_scroll, 1, "Example of `list_namespace`.");
+
+/// Callbacks kept by `appoint_angel`.
+static ANGELS: Mutex<BTreeMap<i64, QCallback>>=Mutex::new(BTreeMap::new());
+
+/// Example of `QCallback`.
+#[no_mangle]
+pub extern "C" fn appoint_angel(id: K, message: K) -> K{
+  let id=match id.get_long(){
+    Ok(id) => id,
+    Err(error) => return unsafe{krr(null_terminated_str_to_const_S(error))}
+  };
+  match unsafe{QCallback::new(message, 1)}{
+    Ok(callback) => {
+      ANGELS.lock().unwrap().insert(id, callback);
+      KNULL!()
+    },
+    Err(error) => error.to_q()
+  }
+}
+export!(".capi", appoint_angel, 2, "Example of `QCallback`.");
+
+/// Example of `QCallback::call`.
+#[no_mangle]
+pub extern "C" fn send_angel(id: K, listener: K) -> K{
+  let angels=ANGELS.lock().unwrap();
+  let angel=match id.get_long().map(|id| angels.get(&id)){
+    Ok(Some(angel)) => angel,
+    _ => return unsafe{krr(null_terminated_str_to_const_S("no such angel\0"))}
+  };
+  match angel.call(&[unsafe{KOwned::from_borrowed(listener)}]){
+    Ok(message) => message.into_raw(),
+    Err(error) => error.to_q()
+  }
+}
+export!(".capi", send_angel, 2, "Example of `QCallback::call`.");
+
+/// Example of dropping `QCallback`.
+#[no_mangle]
+pub extern "C" fn dismiss_angel(id: K) -> K{
+  // The reference of the function is released here.
+  let dismissed=id.get_long().map(|id| ANGELS.lock().unwrap().remove(&id).is_some()).unwrap_or(false);
+  unsafe{kb(dismissed as I)}
+}
+export!(".capi", dismiss_angel, 1, "Example of dropping `QCallback`.");
 
 //++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
 //                            IPC Functions                             //
@@ -405,28 +641,24 @@ pub extern "C" fn dictionary_list_to_table() -> K{
     k(0, str_to_S!("{[dicts] -1 _ dicts, (::)}"), dicts, KNULL!())
   } 
 }
+export!(".capi", dictionary_list_to_table, 1, "Example of `k`.");
+
+/// Example of `eval`.
+#[no_mangle]
+pub extern "C" fn feed_multitude(loaves: K, fish: K) -> K{
+  match eval("{x*y}", &unsafe{[KOwned::from_borrowed(loaves), KOwned::from_borrowed(fish)]}){
+    Ok(meals) => meals.into_raw(),
+    Err(error) => error.to_q()
+  }
+}
+export!(".capi", feed_multitude, 2, "Example of `eval`.");
 
 /// Callback function to send asynchronous query to a q process which sent a query to the
 ///  caller of this function.
 extern "C" fn counter(socket: I) -> K{
-  let extra_query="show `$\"Counter_punch!!\"".as_bytes();
-  let query_length=extra_query.len();
-  // header (8) + list header (6) + data length
-  let total_length=8+6+query_length;
-  // Buffer
-  let mut message: Vec<u8>=Vec::with_capacity(total_length);
-  // Little endian, async, uncompress, reserved
-  message.extend_from_slice(&[1_u8, 0, 0, 0]);
-  // Total message length
-  message.extend_from_slice(&(total_length as i32).to_le_bytes());
-  // Data type, attribute
-  message.extend_from_slice(&[10_u8, 0]);
-  // Length of data
-  message.extend_from_slice(&(query_length as i32).to_le_bytes());
-  // Data
-  message.extend_from_slice(extra_query);
+  let extra_query=QValue::from("show `$\"Counter_punch!!\"");
   // Send
-  unsafe{send(socket, message.as_slice().as_ptr() as *const c_void, total_length, 0)};
+  send_value(socket, &extra_query, MessageType::Async).expect("oh no");
   KNULL!()
 }
 
@@ -443,6 +675,312 @@ pub extern "C" fn enable_counter(socket: K) -> K{
     }
   }
 }
+export!(".capi", enable_counter, 1, "Example of `sd1`.");
+
+/// Example of `serialize`.
+#[no_mangle]
+pub extern "C" fn rust_serialize(qobject: K, big_endian: K, message_type: K) -> K{
+  let endian=match unsafe{bool::from_q(big_endian)}{
+    Ok(true) => Endian::Big,
+    Ok(false) => Endian::Little,
+    Err(error) => return error.to_q()
+  };
+  let message_type=match message_type.get_int(){
+    Ok(0) => MessageType::Async,
+    Ok(1) => MessageType::Sync,
+    Ok(2) => MessageType::Response,
+    _ => return KError::new("message type").to_q()
+  };
+  match unsafe{serialize(qobject, message_type, endian)}{
+    Ok(message) => {
+      let bytes=unsafe{ktn(qtype::BYTE as I, message.len() as J)};
+      bytes.as_mut_slice::<G>().copy_from_slice(&message);
+      bytes
+    },
+    Err(error) => error.to_q()
+  }
+}
+export!(".capi", rust_serialize, 3, "Example of `serialize`.");
+
+/// Example of `deserialize`.
+#[no_mangle]
+pub extern "C" fn rust_deserialize(bytes: K) -> K{
+  match deserialize(bytes.as_mut_slice::<G>(), &Limits::default()){
+    Ok(qobject) => qobject.into_raw(),
+    Err(error) => error.to_q()
+  }
+}
+export!(".capi", rust_deserialize, 1, "Example of `deserialize`.");
+
+/// Example of `compress`.
+#[no_mangle]
+pub extern "C" fn rust_compress(bytes: K) -> K{
+  match compress(bytes.as_mut_slice::<G>()){
+    Some(compressed) => {
+      let list=unsafe{ktn(qtype::BYTE as I, compressed.len() as J)};
+      list.as_mut_slice::<G>().copy_from_slice(&compressed);
+      list
+    },
+    // Not compressed.
+    None => unsafe{r1(bytes)}
+  }
+}
+export!(".capi", rust_compress, 1, "Example of `compress`.");
+
+/// Example of `is_valid_message`.
+#[no_mangle]
+pub extern "C" fn rust_okx(bytes: K) -> K{
+  unsafe{kb(is_valid_message(bytes.as_mut_slice::<G>()) as I)}
+}
+export!(".capi", rust_okx, 1, "Example of `is_valid_message`.");
+
+/// Example of `Connection`.
+#[no_mangle]
+pub extern "C" fn rust_query(host: K, port: K, query: K) -> K{
+  let host=match host.get_symbol(){
+    Ok(host) => host,
+    Err(error) => return unsafe{krr(null_terminated_str_to_const_S(error))}
+  };
+  let port=match port.get_int(){
+    Ok(port) => port as u16,
+    Err(error) => return unsafe{krr(null_terminated_str_to_const_S(error))}
+  };
+  let result=unsafe{QValue::from_k(query)}.and_then(|query| {
+    let mut connection=Connection::connect(host, port, "", Some(Duration::from_secs(1)))?;
+    connection.query_sync(&query)
+  });
+  match result.and_then(|response| response.to_k()){
+    Ok(response) => response.into_raw(),
+    Err(error) => error.to_q()
+  }
+}
+export!(".capi", rust_query, 3, "Example of `Connection`.");
+
+/// Example of `Server`.
+#[no_mangle]
+pub extern "C" fn rust_serve(_: K) -> K{
+  let server=match Server::bind_with_authentication("127.0.0.1:0", |user, _| user != "intruder"){
+    Ok(server) => server,
+    Err(error) => return error.to_q()
+  };
+  let port=match server.local_addr(){
+    Ok(address) => address.port(),
+    Err(error) => return error.to_q()
+  };
+  std::thread::spawn(move || {
+    // Echo a query except for `oops`.
+    server.serve(|_, _, query| match query{
+      QValue::Symbol(symbol) if symbol == "oops" => Err(KError::new("oops")),
+      query => Ok(query)
+    })
+  });
+  unsafe{ki(port as I)}
+}
+export!(".capi", rust_serve, 1, "Example of `Server`.");
+
+/// Example of `JournalReader`.
+#[no_mangle]
+pub extern "C" fn rust_replay(file: K) -> K{
+  let path=match file.get_symbol(){
+    Ok(path) => path.trim_start_matches(':'),
+    Err(error) => return unsafe{krr(null_terminated_str_to_const_S(error))}
+  };
+  let chunks=JournalReader::open(path).and_then(|journal| journal.collect::<Result<Vec<_>, _>>());
+  match chunks.and_then(|chunks| QValue::from(chunks).to_k()){
+    Ok(chunks) => chunks.into_raw(),
+    Err(error) => error.to_q()
+  }
+}
+export!(".capi", rust_replay, 1, "Example of `JournalReader`.");
+
+/// Example of `count_journal`.
+#[no_mangle]
+pub extern "C" fn rust_count_journal(file: K) -> K{
+  let path=match file.get_symbol(){
+    Ok(path) => path.trim_start_matches(':'),
+    Err(error) => return unsafe{krr(null_terminated_str_to_const_S(error))}
+  };
+  match count_journal(path){
+    // Same as `-11!(-2; file)`.
+    Ok(count) if count.is_valid() => unsafe{kj(count.chunks as J)},
+    Ok(count) => QValue::from(vec![count.chunks as i64, count.valid_length as i64]).to_k().map_or_else(|error| error.to_q(), KOwned::into_raw),
+    Err(error) => error.to_q()
+  }
+}
+export!(".capi", rust_count_journal, 1, "Example of `count_journal`.");
+
+/// Example of `JournalWriter`.
+#[no_mangle]
+pub extern "C" fn rust_log_upd(file: K, table: K, data: K) -> K{
+  let path=match file.get_symbol(){
+    Ok(path) => path.trim_start_matches(':'),
+    Err(error) => return unsafe{krr(null_terminated_str_to_const_S(error))}
+  };
+  let table=match table.get_symbol(){
+    Ok(table) => table,
+    Err(error) => return unsafe{krr(null_terminated_str_to_const_S(error))}
+  };
+  let written=unsafe{QValue::from_k(data)}.and_then(|data| {
+    let mut writer=JournalWriter::open(path, SyncPolicy::Always)?;
+    writer.upd(table, data)?;
+    Ok(writer.chunks())
+  });
+  match written{
+    Ok(chunks) => unsafe{kj(chunks as J)},
+    Err(error) => error.to_q()
+  }
+}
+export!(".capi", rust_log_upd, 3, "Example of `JournalWriter`.");
+
+/// Example of `register_fd`.
+#[no_mangle]
+pub extern "C" fn open_well(_: K) -> K{
+  let mut fds: [RawFd; 2]=[0; 2];
+  if unsafe{libc::pipe(fds.as_mut_ptr())} == -1{
+    return KError::new("failed to open pipe").to_q();
+  }
+  // State carried by the closure.
+  let mut drawn=0;
+  let registered=register_fd(fds[0], move |fd| {
+    let mut buffer=[0_u8; 64];
+    let length=unsafe{libc::read(fd, buffer.as_mut_ptr() as *mut c_void, buffer.len())};
+    drawn+=length.max(0);
+    q!("{.well.drawn: x}", drawn as J).ok()
+  });
+  match registered{
+    // Read end and write end.
+    Ok(_) => fds.to_vec().into_q().into_raw(),
+    Err(error) => {
+      unsafe{libc::close(fds[0])};
+      unsafe{libc::close(fds[1])};
+      error.to_q()
+    }
+  }
+}
+export!(".capi", open_well, 1, "Example of `register_fd`.");
+
+/// Example of `register_fd` writing to the descriptor.
+#[no_mangle]
+pub extern "C" fn pour_well(well: K, water: K) -> K{
+  if well.get_type() != qtype::INT || well.len() != 2 || water.get_type() != qtype::CHAR{
+    return unsafe{krr(null_terminated_str_to_const_S("type\0"))};
+  }
+  let fd=well.as_mut_slice::<I>()[1];
+  let water=water.as_mut_slice::<G>();
+  unsafe{ki(libc::write(fd, water.as_ptr() as *const c_void, water.len()) as I)}
+}
+export!(".capi", pour_well, 2, "Example of `register_fd` writing to the descriptor.");
+
+/// Example of `unregister_fd`.
+#[no_mangle]
+pub extern "C" fn close_well(well: K) -> K{
+  if well.get_type() != qtype::INT || well.len() != 2{
+    return unsafe{krr(null_terminated_str_to_const_S("type\0"))};
+  }
+  let fds=well.as_mut_slice::<I>();
+  // The read end is closed with the registration.
+  let unregistered=unregister_fd(fds[0], true);
+  if unregistered{
+    unsafe{libc::close(fds[1])};
+  }
+  unsafe{kb(unregistered as I)}
+}
+export!(".capi", close_well, 1, "Example of `unregister_fd`.");
+
+/// Channel opened by `consult_oracle`.
+static ORACLE: Mutex<Option<MainThreadSender<(i64, String)>>>=Mutex::new(None);
+
+/// Example of `MainThreadSender`.
+#[no_mangle]
+pub extern "C" fn consult_oracle(questions: K) -> K{
+  let questions=match questions.get_long(){
+    Ok(questions) => questions,
+    Err(error) => return unsafe{krr(null_terminated_str_to_const_S(error))}
+  };
+  // Called on the main thread.
+  let sender=MainThreadSender::new(|(question, answer): (i64, String)| {
+    let recorded: Result<(), KError>=q!("{[question; answer] .oracle.answers[question]: answer}", question, Symbol(answer));
+    if let Err(error)=recorded{
+      eprintln!("oracle: {}", error);
+    }
+  });
+  match sender{
+    Ok(sender) => {
+      *ORACLE.lock().unwrap()=Some(sender.clone());
+      std::thread::spawn(move || {
+        for question in 0..questions{
+          let answer=if question % 2 == 0 {"yes"} else {"no"};
+          sender.send((question, answer.to_string())).expect("oracle is closed");
+        }
+      });
+      KNULL!()
+    },
+    Err(error) => error.to_q()
+  }
+}
+export!(".capi", consult_oracle, 1, "Example of `MainThreadSender`.");
+
+/// Example of `MainThreadSender::close`.
+#[no_mangle]
+pub extern "C" fn silence_oracle(_: K) -> K{
+  match ORACLE.lock().unwrap().take(){
+    Some(sender) => {
+      sender.close();
+      unsafe{kb(sender.is_closed() as I)}
+    },
+    None => unsafe{kb(0)}
+  }
+}
+export!(".capi", silence_oracle, 1, "Example of `MainThreadSender::close`.");
+
+/// Example of `spawn_future`.
+#[no_mangle]
+pub extern "C" fn prophesy(days: K, callback: K) -> K{
+  let days=match days.get_long(){
+    Ok(days) => days,
+    Err(error) => return unsafe{krr(null_terminated_str_to_const_S(error))}
+  };
+  let completion=match unsafe{Completion::callback(callback)}{
+    Ok(completion) => completion,
+    Err(error) => return error.to_q()
+  };
+  let prophecy=async move {
+    // 1 day = 1 millisecond
+    tokio::time::sleep(Duration::from_millis(days.max(0) as u64)).await;
+    if days < 0 {Err(KError::new("no prophecy for the past"))} else {Ok(Symbol(format!("in {} days", days)))}
+  };
+  match spawn_future(prophecy, completion){
+    Ok(token) => token.into_raw(),
+    Err(error) => error.to_q()
+  }
+}
+export!(".capi", prophesy, 2, "Example of `spawn_future`.");
+
+/// Example of `schedule_timer`.
+#[no_mangle]
+pub extern "C" fn blow_trumpets(_: K) -> K{
+  // Blow seven times every 500 microseconds.
+  let mut blown=0;
+  let scheduled=schedule_timer(Duration::from_micros(500), Some(Duration::from_micros(500)), move |id| {
+    blown+=1;
+    println!("trumpet {}", blown);
+    if blown == 7{
+      cancel_timer(id);
+    }
+  });
+  match scheduled{
+    Ok(id) => unsafe{kj(id)},
+    Err(error) => error.to_q()
+  }
+}
+export!(".capi", blow_trumpets, 1, "Example of `schedule_timer`.");
+
+/// Example of `timer_namespace`.
+#[no_mangle]
+pub extern "C" fn timer(_: K) -> K{
+  timer_namespace()
+}
+export!(".capi", timer, 1, "Example of `timer_namespace`.");
 
 //++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
 //                           Reference Count                            //
@@ -460,6 +998,7 @@ pub extern "C" fn idle_man(_: K) -> K{
   // Return null.
   KNULL!()
 }
+export!(".capi", idle_man, 1, "Example of `r0`.");
 
 /// Example of `r1`.
 #[no_mangle]
@@ -474,6 +1013,7 @@ pub extern "C" fn pass_through_cave(pedestrian: K) -> K{
     r1(pedestrian)
   }
 }
+export!(".capi", pass_through_cave, 1, "Example of `r1`.");
   
 //++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
 //                            Miscellaneous                             //
@@ -486,6 +1026,7 @@ pub extern "C" fn rust_parse(dollar: K, type_and_text: K) -> K{
     dot(dollar, type_and_text)
   }
 }
+export!(".capi", rust_parse, 2, "Example of `dot`.");
 
 /// Example of `setm`.
 #[no_mangle]
@@ -516,6 +1057,66 @@ pub extern "C" fn parallel_sym_change(list: K) -> K{
     }
   }
 }
+export!(".capi", parallel_sym_change, 1, "Example of `setm`.");
+
+/// Example of `SymbolLock` and `spawn_q_thread`.
+#[no_mangle]
+pub extern "C" fn parallel_sym_change2(list: K) -> K{
+  // `K` cannot have `Send` because it is a pointer but `k0` does.
+  let mut inner=unsafe{*list};
+  // Lock symbol before creating an internal symbol on another thread.
+  let _lock=SymbolLock::lock();
+  let task=spawn_q_thread(move || {
+    inner.as_mut_slice::<S>()[0]=unsafe{ss(str_to_S!("replaced"))};
+    inner
+  });
+  list.as_mut_slice::<S>()[1]=unsafe{ss(str_to_S!("symbolbol"))};
+  match task.join(){
+    Ok(replaced) => unsafe{
+      *list=replaced;
+      // Increment reference count for copy.
+      r1(list)
+    },
+    Err(_) => KError::new("oh no").to_q()
+  }
+  // Unlocked here.
+}
+export!(".capi", parallel_sym_change2, 1, "Example of `SymbolLock` and `spawn_q_thread`.");
+
+/// Example of `par_map`.
+#[no_mangle]
+pub extern "C" fn discount(prices: K, rate: K) -> K{
+  let rate=match rate.get_float(){
+    Ok(rate) => rate,
+    Err(error) => return unsafe{krr(null_terminated_str_to_const_S(error))}
+  };
+  match par_map(prices, |price: F| price * (1.0 - rate)){
+    Ok(discounted) => discounted.into_raw(),
+    Err(error) => error.to_q()
+  }
+}
+export!(".capi", discount, 2, "Example of `par_map`.");
+
+/// Example of `par_map` keeping a temporal type.
+#[no_mangle]
+pub extern "C" fn next_sabbath(timestamps: K) -> K{
+  const WEEK: J=7 * 86_400_000_000_000;
+  match par_map(timestamps, |timestamp: J| timestamp + WEEK){
+    Ok(shifted) => shifted.into_raw(),
+    Err(error) => error.to_q()
+  }
+}
+export!(".capi", next_sabbath, 1, "Example of `par_map` keeping a temporal type.");
+
+/// Example of `par_map_symbol`.
+#[no_mangle]
+pub extern "C" fn name_tribes(ids: K) -> K{
+  match par_map_symbol(ids, |id: J| format!("tribe{}", id)){
+    Ok(names) => names.into_raw(),
+    Err(error) => error.to_q()
+  }
+}
+export!(".capi", name_tribes, 1, "Example of `par_map_symbol`.");
 
 /// Example of `ymd`.
 #[no_mangle]
@@ -525,6 +1126,7 @@ pub extern "C" fn ymd_to_days(_: K) -> K{
     ki(days) 
   }
 }
+export!(".capi", ymd_to_days, 1, "Example of `ymd`.");
 
 /// Example of `dj`.
 #[no_mangle]
@@ -534,6 +1136,19 @@ pub extern "C" fn days_to_date(days: K) -> K{
     ki(number)
   }
 }
+export!(".capi", days_to_date, 1, "Example of `dj`.");
+
+/// Function passed to q by `dl`.
+extern "C" fn add_one(x: K) -> K{
+  unsafe{kj(x.get_long().unwrap_or(0) + 1)}
+}
+
+/// Example of `dl`.
+#[no_mangle]
+pub extern "C" fn give_me_function(_: K) -> K{
+  unsafe{dl(add_one as *const V, 1)}
+}
+export!(".capi", give_me_function, 1, "Example of `dl`.");
 
 /// Example of `S_to_str`.
 #[no_mangle]
@@ -546,6 +1161,7 @@ pub extern "C" fn print_symbol(symbol: K) -> K{
     KNULL!()
   }
 }
+export!(".capi", print_symbol, 1, "Example of `S_to_str`.");
 
 //++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
 //                              Utility                                 //
@@ -558,6 +1174,7 @@ pub extern "C" fn bigbang2(_: K) -> K{
     ks(null_terminated_str_to_S("super_illusion\0"))
   }
 }
+export!(".capi", bigbang2, 1, "Example of `null_terminated_str_to_S`.");
 
 //++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
 //                              Re-export                               //
@@ -569,6 +1186,7 @@ pub extern "C" fn create_month(_: K) -> K{
   // 2010.07m
   new_month(126)
 }
+export!(".capi", create_month, 1, "Example of `new_month`.");
 
 /// Example of `new_minute`.
 #[no_mangle]
@@ -576,6 +1194,7 @@ pub extern "C" fn create_minute(_: K) -> K{
   // 10:40
   new_minute(640)
 }
+export!(".capi", create_minute, 1, "Example of `new_minute`.");
 
 /// Example of `new_second`.
 #[no_mangle]
@@ -583,3 +1202,18 @@ pub extern "C" fn create_second(_: K) -> K{
   // -02:00:00
   new_second(-7200)
 }
+export!(".capi", create_second, 1, "Example of `new_second`.");
+
+/// Example of `new_foreign`.
+#[no_mangle]
+pub extern "C" fn create_pilgrimage(_: K) -> K{
+  new_foreign(Pilgrimage{steps: 0})
+}
+export!(".capi", create_pilgrimage, 1, "Example of `new_foreign`.");
+
+/// Example of `new_foreign` wrapping an object of another type.
+#[no_mangle]
+pub extern "C" fn create_scroll(_: K) -> K{
+  new_foreign(String::from("Lamentations"))
+}
+export!(".capi", create_scroll, 1, "Example of `new_foreign` wrapping an object of another type.");
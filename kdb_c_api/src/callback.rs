@@ -0,0 +1,158 @@
+//! This module provides `QCallback`, a q function kept in Rust to be invoked later, e.g., from a
+//!  callback registered by [`sd1`](../fn.sd1.html) or on a timer.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Load Libraries                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use super::*;
+use std::thread::{self, ThreadId};
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                               Structs                                //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// q function kept in Rust. A reference count of the function is incremented on creation and
+///  decremented when it is dropped.
+///
+/// `QCallback` can be moved to another thread to be stored in a registry shared among threads,
+///  but the function can be invoked only on the thread where it was created, i.e., the main thread
+///  of q. If it is dropped on another thread, the reference is leaked rather than released without
+///  the lock of q memory.
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+/// use std::collections::BTreeMap;
+/// use std::sync::Mutex;
+///
+/// static ANGELS: Mutex<BTreeMap<i64, QCallback>>=Mutex::new(BTreeMap::new());
+///
+/// #[no_mangle]
+/// pub extern "C" fn appoint_angel(id: K, message: K) -> K{
+///   let id=match id.get_long(){
+///     Ok(id) => id,
+///     Err(error) => return unsafe{krr(null_terminated_str_to_const_S(error))}
+///   };
+///   match unsafe{QCallback::new(message, 1)}{
+///     Ok(callback) => {
+///       ANGELS.lock().unwrap().insert(id, callback);
+///       KNULL!()
+///     },
+///     Err(error) => error.to_q()
+///   }
+/// }
+///
+/// #[no_mangle]
+/// pub extern "C" fn send_angel(id: K, listener: K) -> K{
+///   let angels=ANGELS.lock().unwrap();
+///   let angel=match id.get_long().map(|id| angels.get(&id)){
+///     Ok(Some(angel)) => angel,
+///     _ => return unsafe{krr(null_terminated_str_to_const_S("no such angel\0"))}
+///   };
+///   match angel.call(&[unsafe{KOwned::from_borrowed(listener)}]){
+///     Ok(message) => message.into_raw(),
+///     Err(error) => error.to_q()
+///   }
+/// }
+///
+/// #[no_mangle]
+/// pub extern "C" fn dismiss_angel(id: K) -> K{
+///   // The reference of the function is released here.
+///   let dismissed=id.get_long().map(|id| ANGELS.lock().unwrap().remove(&id).is_some()).unwrap_or(false);
+///   unsafe{kb(dismissed as I)}
+/// }
+/// ```
+/// ```q
+/// q)appoint_angel: LIBPATH_ (`appoint_angel; 2);
+/// q)send_angel: LIBPATH_ (`send_angel; 2);
+/// q)dismiss_angel: LIBPATH_ (`dismiss_angel; 1);
+/// q)appoint_angel[7; {"Fear not, ", x}]
+/// q)send_angel[7; "Mary"]
+/// "Fear not, Mary"
+/// q)appoint_angel[8; {x, y}]
+/// 'rank
+/// q)dismiss_angel[7]
+/// 1b
+/// ```
+#[derive(Debug)]
+pub struct QCallback{
+  /// q function whose reference is owned.
+  function: K,
+  /// Number of arguments.
+  arity: usize,
+  /// Thread where the callback was created.
+  owner: ThreadId
+}
+
+// The function is touched only on the owner thread.
+unsafe impl Send for QCallback{}
+unsafe impl Sync for QCallback{}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Implementation                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+impl QCallback{
+  /// Keep a q function with an expected number of arguments. `'type` is returned if the object is not
+  ///  a function and `'rank` if the arity of the function is known and it does not match `arity`.
+  ///  The arity of a lambda, a projection of a lambda, a unary primitive and a binary operator is checked.
+  ///  A function loaded by `2:` is accepted without checking the arity, but a foreign object created by
+  ///  [`new_foreign`](fn.new_foreign.html), which is also `112h`, is not a function.
+  /// # Safety
+  /// `function` must be a valid q object.
+  /// # Note
+  /// This function must be called on the main thread of q.
+  pub unsafe fn new(function: K, arity: usize) -> Result<Self, KError>{
+    // Types from 100h (lambda) to 112h (dynamic load) except for foreign objects sharing 112h.
+    if !(qtype::LAMBDA..=qtype::FOREIGN).contains(&function.get_type()) || is_rust_foreign(function){
+      return Err(KError::new("type"));
+    }
+    // -1 if the arity cannot be determined, e.g., a function loaded by `2:`.
+    let actual: J=q!("{$[100h = t: type x; count value[x] 1; 101h = t; 1; 102h = t; 2; 104h = t; $[-1 < a: .z.s first v: value x; a - sum not (::) ~/: 1 _ v; -1]; -1]}", KOwned::from_borrowed(function))?;
+    if actual >= 0 && actual as usize != arity{
+      return Err(KError::new("rank"));
+    }
+    Ok(QCallback{
+      function: r1(function),
+      arity,
+      owner: thread::current().id()
+    })
+  }
+
+  /// Number of arguments the function takes.
+  pub fn arity(&self) -> usize{
+    self.arity
+  }
+
+  /// Check if the current thread is the thread where the callback was created.
+  pub fn is_owner_thread(&self) -> bool{
+    thread::current().id() == self.owner
+  }
+
+  /// Invoke the function with arguments by [`apply`](fn.apply.html). `'rank` is returned if the number
+  ///  of the arguments does not match the arity.
+  /// # Note
+  /// An error is returned if this method is called on a thread other than the main thread of q.
+  pub fn call(&self, args: &[KOwned]) -> Result<KOwned, KError>{
+    if !self.is_owner_thread(){
+      return Err(KError::new("callback must be invoked on the main thread"));
+    }
+    if args.len() != self.arity{
+      return Err(KError::new("rank"));
+    }
+    unsafe{apply(self.function, args)}
+  }
+
+  /// Get the underlying q function without releasing the reference.
+  pub fn as_k(&self) -> K{
+    self.function
+  }
+}
+
+impl Drop for QCallback{
+  fn drop(&mut self){
+    if self.is_owner_thread(){
+      unsafe{r0(self.function)};
+    }
+  }
+}
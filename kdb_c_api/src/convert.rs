@@ -0,0 +1,337 @@
+//! This module provides conversion between Rust values and q objects used by [`q`](macro.q.html).
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Load Libraries                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use super::*;
+use std::ffi::CString;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                               Structs                                //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// q symbol. Rust strings are converted into q strings (char lists) while this
+///  type is converted into a q symbol. A symbol is truncated at a null character as q reads it.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Symbol(pub String);
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                                Traits                                //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Conversion of a Rust value into q object.
+pub trait IntoQ{
+  /// Convert into a newly created q object.
+  fn into_q(self) -> KOwned;
+}
+
+/// Conversion of q object into a Rust value.
+pub trait FromQ: Sized{
+  /// Convert a q object into a Rust value. The q object is not consumed.
+  /// # Safety
+  /// `qobject` must be a valid q object.
+  unsafe fn from_q(qobject: K) -> Result<Self, KError>;
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Implementation                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+//%% IntoQ %%//vvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvv/
+
+impl IntoQ for KOwned{
+  fn into_q(self) -> KOwned{
+    self
+  }
+}
+
+impl IntoQ for &KOwned{
+  fn into_q(self) -> KOwned{
+    self.clone()
+  }
+}
+
+impl IntoQ for (){
+  fn into_q(self) -> KOwned{
+    unsafe{
+      let null=KOwned::from_raw(ka(qtype::NULL as I));
+      (*null.as_k()).value.long=0;
+      null
+    }
+  }
+}
+
+impl IntoQ for bool{
+  fn into_q(self) -> KOwned{
+    unsafe{KOwned::from_raw(kb(self as I))}
+  }
+}
+
+impl IntoQ for u8{
+  fn into_q(self) -> KOwned{
+    unsafe{KOwned::from_raw(kg(self as I))}
+  }
+}
+
+impl IntoQ for i16{
+  fn into_q(self) -> KOwned{
+    unsafe{KOwned::from_raw(kh(self as I))}
+  }
+}
+
+impl IntoQ for i32{
+  fn into_q(self) -> KOwned{
+    unsafe{KOwned::from_raw(ki(self))}
+  }
+}
+
+impl IntoQ for i64{
+  fn into_q(self) -> KOwned{
+    unsafe{KOwned::from_raw(kj(self))}
+  }
+}
+
+impl IntoQ for f32{
+  fn into_q(self) -> KOwned{
+    unsafe{KOwned::from_raw(ke(self as F))}
+  }
+}
+
+impl IntoQ for f64{
+  fn into_q(self) -> KOwned{
+    unsafe{KOwned::from_raw(kf(self))}
+  }
+}
+
+impl IntoQ for char{
+  /// Always converted into a string of UTF-8 bytes, e.g., `'a'` into `,"a"`, since q char holds a single
+  ///  byte. The q type does not depend on the character.
+  fn into_q(self) -> KOwned{
+    self.encode_utf8(&mut [0_u8; 4]).into_q()
+  }
+}
+
+impl IntoQ for &str{
+  fn into_q(self) -> KOwned{
+    unsafe{KOwned::from_raw(kpn(self.as_ptr() as S, self.len() as J))}
+  }
+}
+
+impl IntoQ for String{
+  fn into_q(self) -> KOwned{
+    self.as_str().into_q()
+  }
+}
+
+impl IntoQ for Symbol{
+  fn into_q(self) -> KOwned{
+    let symbol=symbol_to_cstring(self.0);
+    unsafe{KOwned::from_raw(ks(symbol.as_ptr() as S))}
+  }
+}
+
+/// Implement `IntoQ` for a vector of a simple type.
+macro_rules! impl_into_q_for_vec {
+  ($rust: ty, $qtype: expr, $element: ty) => {
+    impl IntoQ for Vec<$rust>{
+      fn into_q(self) -> KOwned{
+        unsafe{
+          let list=KOwned::from_raw(ktn($qtype as I, self.len() as J));
+          for (item, value) in list.as_mut_slice::<$element>().iter_mut().zip(self){
+            *item=value as $element;
+          }
+          list
+        }
+      }
+    }
+  };
+}
+
+impl_into_q_for_vec!(bool, qtype::BOOL, G);
+impl_into_q_for_vec!(u8, qtype::BYTE, G);
+impl_into_q_for_vec!(i16, qtype::SHORT, H);
+impl_into_q_for_vec!(i32, qtype::INT, I);
+impl_into_q_for_vec!(i64, qtype::LONG, J);
+impl_into_q_for_vec!(f32, qtype::REAL, E);
+impl_into_q_for_vec!(f64, qtype::FLOAT, F);
+
+impl IntoQ for Vec<Symbol>{
+  fn into_q(self) -> KOwned{
+    unsafe{
+      let list=KOwned::from_raw(ktn(qtype::SYMBOL as I, self.len() as J));
+      for (item, symbol) in list.as_mut_slice::<S>().iter_mut().zip(self){
+        let symbol=symbol_to_cstring(symbol.0);
+        *item=ss(symbol.as_ptr() as S);
+      }
+      list
+    }
+  }
+}
+
+impl IntoQ for Vec<String>{
+  /// Converted into a list of strings.
+  fn into_q(self) -> KOwned{
+    unsafe{
+      let list=KOwned::from_raw(ktn(qtype::COMPOUND as I, self.len() as J));
+      for (item, string) in list.as_mut_slice::<K>().iter_mut().zip(self){
+        *item=string.into_q().into_raw();
+      }
+      list
+    }
+  }
+}
+
+//%% FromQ %%//vvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvv/
+
+impl FromQ for KOwned{
+  unsafe fn from_q(qobject: K) -> Result<Self, KError>{
+    Ok(KOwned::from_borrowed(qobject))
+  }
+}
+
+impl FromQ for (){
+  /// Any q object is accepted and discarded.
+  unsafe fn from_q(_: K) -> Result<Self, KError>{
+    Ok(())
+  }
+}
+
+impl FromQ for bool{
+  unsafe fn from_q(qobject: K) -> Result<Self, KError>{
+    match qobject.get_type(){
+      t if t == -qtype::BOOL => Ok((*qobject).value.byte != 0),
+      _ => Err(KError::new("not a bool"))
+    }
+  }
+}
+
+impl FromQ for u8{
+  unsafe fn from_q(qobject: K) -> Result<Self, KError>{
+    Ok(qobject.get_byte()?)
+  }
+}
+
+impl FromQ for i16{
+  unsafe fn from_q(qobject: K) -> Result<Self, KError>{
+    Ok(qobject.get_short()?)
+  }
+}
+
+impl FromQ for i32{
+  unsafe fn from_q(qobject: K) -> Result<Self, KError>{
+    Ok(qobject.get_int()?)
+  }
+}
+
+impl FromQ for i64{
+  unsafe fn from_q(qobject: K) -> Result<Self, KError>{
+    Ok(qobject.get_long()?)
+  }
+}
+
+impl FromQ for f32{
+  unsafe fn from_q(qobject: K) -> Result<Self, KError>{
+    Ok(qobject.get_real()?)
+  }
+}
+
+impl FromQ for f64{
+  unsafe fn from_q(qobject: K) -> Result<Self, KError>{
+    Ok(qobject.get_float()?)
+  }
+}
+
+impl FromQ for char{
+  /// Both a char and a string of a single UTF-8 character converted from `char` are accepted.
+  unsafe fn from_q(qobject: K) -> Result<Self, KError>{
+    if qobject.get_type() == qtype::CHAR{
+      let mut chars=std::str::from_utf8(qobject.as_mut_slice::<G>()).map_err(|_| KError::new("not a char"))?.chars();
+      return match (chars.next(), chars.next()){
+        (Some(char), None) => Ok(char),
+        _ => Err(KError::new("not a char"))
+      };
+    }
+    Ok(qobject.get_char()?)
+  }
+}
+
+impl FromQ for String{
+  /// Both a string and a symbol are accepted.
+  unsafe fn from_q(qobject: K) -> Result<Self, KError>{
+    match qobject.get_type(){
+      qtype::CHAR => Ok(String::from_utf8_lossy(qobject.as_mut_slice::<G>()).into_owned()),
+      t if t == -qtype::SYMBOL => Ok(qobject.get_symbol()?.to_string()),
+      _ => Err(KError::new("not a string"))
+    }
+  }
+}
+
+impl FromQ for Symbol{
+  unsafe fn from_q(qobject: K) -> Result<Self, KError>{
+    Ok(Symbol(qobject.get_symbol()?.to_string()))
+  }
+}
+
+/// Implement `FromQ` for a vector of a simple type.
+macro_rules! impl_from_q_for_vec {
+  ($rust: ty, [$($qtype: expr),+], $element: ty, $error: expr) => {
+    impl FromQ for Vec<$rust>{
+      unsafe fn from_q(qobject: K) -> Result<Self, KError>{
+        match qobject.get_type(){
+          $(t if t == $qtype => Ok(qobject.as_mut_slice::<$element>().iter().map(|&item| item as $rust).collect()),)+
+          _ => Err(KError::new($error))
+        }
+      }
+    }
+  };
+}
+
+impl_from_q_for_vec!(u8, [qtype::BYTE], G, "not a byte list");
+impl_from_q_for_vec!(i16, [qtype::SHORT], H, "not a short list");
+impl_from_q_for_vec!(i32, [qtype::INT, qtype::MONTH, qtype::DATE, qtype::MINUTE, qtype::SECOND, qtype::TIME], I, "not an int list");
+impl_from_q_for_vec!(i64, [qtype::LONG, qtype::TIMESTAMP, qtype::TIMESPAN], J, "not a long list");
+impl_from_q_for_vec!(f32, [qtype::REAL], E, "not a real list");
+impl_from_q_for_vec!(f64, [qtype::FLOAT, qtype::DATETIME], F, "not a float list");
+
+impl FromQ for Vec<bool>{
+  unsafe fn from_q(qobject: K) -> Result<Self, KError>{
+    match qobject.get_type(){
+      qtype::BOOL => Ok(qobject.as_mut_slice::<G>().iter().map(|&item| item != 0).collect()),
+      _ => Err(KError::new("not a bool list"))
+    }
+  }
+}
+
+impl FromQ for Vec<Symbol>{
+  unsafe fn from_q(qobject: K) -> Result<Self, KError>{
+    match qobject.get_type(){
+      qtype::SYMBOL => Ok(qobject.as_mut_slice::<S>().iter().map(|&symbol| Symbol(S_to_str(symbol).to_string())).collect()),
+      _ => Err(KError::new("not a symbol list"))
+    }
+  }
+}
+
+impl FromQ for Vec<String>{
+  /// Both a list of strings and a symbol list are accepted.
+  unsafe fn from_q(qobject: K) -> Result<Self, KError>{
+    match qobject.get_type(){
+      qtype::SYMBOL => Ok(qobject.as_mut_slice::<S>().iter().map(|&symbol| S_to_str(symbol).to_string()).collect()),
+      qtype::COMPOUND => qobject.as_mut_slice::<K>().iter().map(|&string| String::from_q(string)).collect(),
+      _ => Err(KError::new("not a list of strings"))
+    }
+  }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                              Utility                                 //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Copy a symbol into a null-terminated string truncated at the first null character.
+pub(crate) fn symbol_to_cstring(symbol: String) -> CString{
+  let mut bytes=symbol.into_bytes();
+  if let Some(end)=bytes.iter().position(|&byte| byte == 0){
+    bytes.truncate(end);
+  }
+  CString::new(bytes).unwrap_or_default()
+}
@@ -0,0 +1,228 @@
+//! This module provides safe wrappers of evaluation on the q process which loads the shared library.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Load Libraries                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use super::*;
+use std::ffi::CString;
+use std::sync::atomic::{AtomicPtr, AtomicU8, Ordering};
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                          Global Variables                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Maximum number of arguments of q function.
+pub const MAX_ARITY: usize=8;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                               Structs                                //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Cache of a q function parsed from a text query. The query is evaluated on the first use and
+///  the result is kept until the process exits. Used by [`q`](macro.q.html) to avoid parsing a
+///  query on every call.
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+///
+/// #[no_mangle]
+/// pub extern "C" fn drop_last(dicts: K) -> K{
+///   static DROP_LAST: LambdaCache=LambdaCache::new();
+///   match DROP_LAST.apply("{[dicts] -1 _ dicts, (::)}", &[unsafe{KOwned::from_borrowed(dicts)}]){
+///     Ok(result) => result.into_raw(),
+///     Err(error) => error.to_q()
+///   }
+/// }
+/// ```
+pub struct LambdaCache(AtomicPtr<k0>);
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                              Evaluation                              //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Evaluate a text query with arguments by `k(0, ...)` on the q process which loads the shared library.
+///  The trailing null pointer required by `k` is appended and a reference of each argument is
+///  incremented before passing it to `k`, which consumes it; therefore the arguments are still
+///  available after the call. Up to 8 arguments can be passed. q error is returned as `KError`.
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+///
+/// #[no_mangle]
+/// pub extern "C" fn feed_multitude(loaves: K, fish: K) -> K{
+///   match eval("{x*y}", &unsafe{[KOwned::from_borrowed(loaves), KOwned::from_borrowed(fish)]}){
+///     Ok(meals) => meals.into_raw(),
+///     Err(error) => error.to_q()
+///   }
+/// }
+/// ```
+/// ```q
+/// q)feed_multitude: LIBPATH_ (`feed_multitude; 2);
+/// q)feed_multitude[5; 2]
+/// 10
+/// q)feed_multitude[5; `fish]
+/// 'type
+/// ```
+/// # Note
+/// This function must be called on the main thread of q.
+pub fn eval(query: &str, args: &[KOwned]) -> Result<KOwned, KError>{
+  if args.len() > MAX_ARITY{
+    return Err(KError::new("rank"));
+  }
+  let query=CString::new(query).map_err(|_| KError::new("query contains a null character"))?;
+  let query=query.as_ptr();
+  let arg=|i: usize| unsafe{r1(args[i].as_k())};
+  unsafe{
+    let result=match args.len(){
+      0 => k(0, query, KNULL!()),
+      1 => k(0, query, arg(0), KNULL!()),
+      2 => k(0, query, arg(0), arg(1), KNULL!()),
+      3 => k(0, query, arg(0), arg(1), arg(2), KNULL!()),
+      4 => k(0, query, arg(0), arg(1), arg(2), arg(3), KNULL!()),
+      5 => k(0, query, arg(0), arg(1), arg(2), arg(3), arg(4), KNULL!()),
+      6 => k(0, query, arg(0), arg(1), arg(2), arg(3), arg(4), arg(5), KNULL!()),
+      7 => k(0, query, arg(0), arg(1), arg(2), arg(3), arg(4), arg(5), arg(6), KNULL!()),
+      _ => k(0, query, arg(0), arg(1), arg(2), arg(3), arg(4), arg(5), arg(6), arg(7), KNULL!())
+    };
+    KOwned::from_result(result)
+  }
+}
+
+/// Apply a function to arguments with protected evaluation (`.[func; args]` trapping an error). The arguments
+///  are passed as a slice instead of a mixed list. On failure, q error message is returned as `KError`
+///  with q backtrace if `.Q.trp` is available (kdb+ 3.5+).
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+///
+/// #[no_mangle]
+/// pub extern "C" fn catchy2(func: K, arg1: K, arg2: K) -> K{
+///   match unsafe{apply(func, &[KOwned::from_borrowed(arg1), KOwned::from_borrowed(arg2)])}{
+///     Ok(result) => result.into_raw(),
+///     Err(error) => {
+///       println!("error: {}", error);
+///       if let Some(backtrace)=error.backtrace(){
+///         println!("{}", backtrace);
+///       }
+///       KNULL!()
+///     }
+///   }
+/// }
+/// ```
+/// ```q
+/// q)catchy2: LIBPATH_ (`catchy2; 3);
+/// q)catchy2[$; "J"; "42"]
+/// 42
+/// q)catchy2[+; 1; `a]
+/// error: type
+///   [0]  +
+///        ^
+/// ```
+/// # Safety
+/// `func` must be a valid q object.
+/// # Note
+/// This function must be called on the main thread of q.
+pub unsafe fn apply(func: K, args: &[KOwned]) -> Result<KOwned, KError>{
+  let list=argument_list(args);
+  if !has_trap(){
+    return KOwned::from_result(ee(dot(func, list.as_k())));
+  }
+  // Result is `(1b; result; ::)` on success or `(0b; message; backtrace)` on failure. The trailing `::`
+  //  keeps the list mixed; otherwise a bool atom result would collapse it into a bool list.
+  static TRAP: LambdaCache=LambdaCache::new();
+  let trap=TRAP.get("{[func; args] .Q.trp[{(1b; x . y; ::)}[func]; args; {(0b; x; .Q.sbt y)}]}")?;
+  let traced=KOwned::from_result(ee(dot(trap, argument_list(&[KOwned::from_borrowed(func), list]).as_k())))?;
+  let items=traced.as_mut_slice::<K>();
+  if (*items[0]).value.byte != 0{
+    Ok(KOwned::from_borrowed(items[1]))
+  }
+  else{
+    Err(KError::with_backtrace(items[1].get_string().unwrap_or_default(), items[2].get_string().unwrap_or_default()))
+  }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Implementation                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+impl LambdaCache{
+  /// Create an empty cache. The query is given on use.
+  pub const fn new() -> Self{
+    LambdaCache(AtomicPtr::new(std::ptr::null_mut()))
+  }
+
+  /// Get the function parsed from the query. The query is evaluated only on the first call and the
+  ///  cached function is returned after that; therefore the same query must be passed every time.
+  /// # Note
+  /// This function must be called on the main thread of q.
+  pub fn get(&self, query: &str) -> Result<K, KError>{
+    let cached=self.0.load(Ordering::Acquire);
+    if !cached.is_null(){
+      return Ok(cached);
+    }
+    let function=eval(query, &[])?.into_raw();
+    match self.0.compare_exchange(std::ptr::null_mut(), function, Ordering::AcqRel, Ordering::Acquire){
+      Ok(_) => Ok(function),
+      Err(cached) => {
+        unsafe{r0(function)};
+        Ok(cached)
+      }
+    }
+  }
+
+  /// Apply the function parsed from the query to arguments with [`apply`](fn.apply.html).
+  /// # Note
+  /// This function must be called on the main thread of q.
+  pub fn apply(&self, query: &str, args: &[KOwned]) -> Result<KOwned, KError>{
+    // The cached function is kept alive until the process exits.
+    unsafe{apply(self.get(query)?, args)}
+  }
+}
+
+impl Default for LambdaCache{
+  fn default() -> Self{
+    LambdaCache::new()
+  }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                              Utility                                 //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Build a mixed list of arguments passed to `dot`.
+fn argument_list(args: &[KOwned]) -> KOwned{
+  unsafe{
+    if args.is_empty(){
+      // Apply to (::) as `func[]` does.
+      let list=KOwned::from_raw(ktn(qtype::COMPOUND as I, 1));
+      list.as_mut_slice::<K>()[0]=ka(qtype::NULL as I);
+      (*list.as_mut_slice::<K>()[0]).value.long=0;
+      list
+    }
+    else{
+      let list=KOwned::from_raw(ktn(qtype::COMPOUND as I, args.len() as J));
+      for (item, arg) in list.as_mut_slice::<K>().iter_mut().zip(args){
+        *item=r1(arg.as_k());
+      }
+      list
+    }
+  }
+}
+
+/// Check if `.Q.trp` is available. The result is cached.
+fn has_trap() -> bool{
+  // 0: unknown, 1: available, 2: not available
+  static HAS_TRAP: AtomicU8=AtomicU8::new(0);
+  match HAS_TRAP.load(Ordering::Relaxed){
+    0 => {
+      let available=match eval("`trp in key `.Q", &[]){
+        Ok(available) => unsafe{(*available.as_k()).value.byte != 0},
+        Err(_) => false
+      };
+      HAS_TRAP.store(if available {1} else {2}, Ordering::Relaxed);
+      available
+    },
+    status => status == 1
+  }
+}
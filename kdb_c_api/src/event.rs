@@ -0,0 +1,332 @@
+//! This module provides registration of Rust closures to the main event loop of q. A file descriptor,
+//!  e.g., a socket, a pipe, a timerfd or an inotify descriptor, is hooked by [`sd1`](../fn.sd1.html)
+//!  with a single trampoline function which dispatches the event to the closure registered for the
+//!  descriptor. Unlike a bare function passed to `sd1`, the closure can carry its state.
+//!
+//! On Linux, [`MainThreadSender`](../struct.MainThreadSender.html) delivers values from background
+//!  threads to the main thread of q through an `eventfd` hooked in the same way.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Load Libraries                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use super::*;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::os::unix::io::RawFd;
+#[cfg(target_os="linux")]
+use std::collections::VecDeque;
+#[cfg(target_os="linux")]
+use std::sync::{Arc, Mutex};
+#[cfg(target_os="linux")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(target_os="linux")]
+use std::sync::mpsc::SendError;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                          Global Variables                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Closure called when a registered file descriptor gets ready.
+type Handler=Box<dyn FnMut(RawFd) -> Option<KOwned>>;
+
+thread_local!{
+  /// Closures keyed by file descriptor. A slot is empty while the closure is running so that the
+  ///  closure can register or unregister descriptors including its own.
+  static HANDLERS: RefCell<BTreeMap<RawFd, Option<Handler>>>=const {RefCell::new(BTreeMap::new())};
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                               Structs                                //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Sender delivering values from background threads to the main thread of q. Sent values are queued
+///  and an `eventfd` hooked to the main event loop of q by [`register_fd`](fn.register_fd.html) is
+///  notified. On the next loop, the handler given at creation receives the queued values one by one on the
+///  main thread, where it can convert them into q objects and call q functions. Since `K` cannot be sent
+///  to another thread, values are plain Rust values or serialized bytes.
+///
+/// The sender can be cloned and shared among threads. The channel is open until
+///  [`close`](struct.MainThreadSender.html#method.close) is called on the main thread or every sender is
+///  dropped, in which case the channel is closed on the next loop after the values sent before are
+///  delivered.
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+///
+/// #[no_mangle]
+/// pub extern "C" fn consult_oracle(questions: K) -> K{
+///   let questions=match questions.get_long(){
+///     Ok(questions) => questions,
+///     Err(error) => return unsafe{krr(null_terminated_str_to_const_S(error))}
+///   };
+///   // Called on the main thread.
+///   let sender=MainThreadSender::new(|(question, answer): (i64, String)| {
+///     let recorded: Result<(), KError>=q!("{[question; answer] .oracle.answers[question]: answer}", question, Symbol(answer));
+///     if let Err(error)=recorded{
+///       eprintln!("oracle: {}", error);
+///     }
+///   });
+///   match sender{
+///     Ok(sender) => {
+///       std::thread::spawn(move || {
+///         for question in 0..questions{
+///           let answer=if question % 2 == 0 {"yes"} else {"no"};
+///           sender.send((question, answer.to_string())).expect("oracle is closed");
+///         }
+///       });
+///       KNULL!()
+///     },
+///     Err(error) => error.to_q()
+///   }
+/// }
+/// ```
+/// ```q
+/// q)consult_oracle: LIBPATH_ (`consult_oracle; 1);
+/// q).oracle.answers: (`long$())!`symbol$();
+/// q)consult_oracle[3]
+/// q)// On the next loop
+/// q).oracle.answers
+/// 0| yes
+/// 1| no
+/// 2| yes
+/// ```
+#[cfg(target_os="linux")]
+pub struct MainThreadSender<T>{
+  shared: Arc<Channel<T>>
+}
+
+/// State shared between senders and the handler on the main thread.
+#[cfg(target_os="linux")]
+struct Channel<T>{
+  /// Queued values. `None` after the channel is closed.
+  queue: Mutex<Option<VecDeque<T>>>,
+  /// `eventfd` to wake up the main event loop of q.
+  fd: RawFd,
+  /// Number of senders. The handler closes the channel when it reaches zero.
+  senders: AtomicUsize
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Implementation                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+#[cfg(target_os="linux")]
+impl<T: Send + 'static> MainThreadSender<T>{
+  /// Open a channel whose values are passed to `handler` on the main thread of q.
+  /// # Note
+  /// This function must be called on the main thread of q.
+  pub fn new<F>(mut handler: F) -> Result<Self, KError> where F: FnMut(T) + 'static{
+    let fd=unsafe{libc::eventfd(0, libc::EFD_NONBLOCK | libc::EFD_CLOEXEC)};
+    if fd == -1{
+      return Err(KError::new(format!("failed to create eventfd: {}", std::io::Error::last_os_error())));
+    }
+    let shared=Arc::new(Channel{queue: Mutex::new(Some(VecDeque::new())), fd, senders: AtomicUsize::new(1)});
+    let receiver=Arc::clone(&shared);
+    let registered=register_fd(fd, move |fd| {
+      // Reset the counter of eventfd.
+      let mut counter=0_u64;
+      unsafe{libc::read(fd, &mut counter as *mut u64 as *mut libc::c_void, 8)};
+      // No value is sent after the last sender is dropped.
+      let orphaned=receiver.senders.load(Ordering::SeqCst) == 0;
+      // Release the lock before calling the handler so that it can send a value.
+      let values=receiver.queue.lock().unwrap().as_mut().map(std::mem::take).unwrap_or_default();
+      values.into_iter().for_each(&mut handler);
+      // The handler holding the channel is dropped with the registration.
+      if orphaned && receiver.queue.lock().unwrap().take().is_some(){
+        unregister_fd(fd, true);
+      }
+      None
+    });
+    if let Err(error)=registered{
+      unsafe{libc::close(fd)};
+      return Err(error);
+    }
+    Ok(MainThreadSender{shared})
+  }
+
+  /// Send a value to the main thread. The value is given back as an error if the channel is closed.
+  pub fn send(&self, value: T) -> Result<(), SendError<T>>{
+    let mut queue=self.shared.queue.lock().unwrap();
+    match queue.as_mut(){
+      Some(queue) => queue.push_back(value),
+      None => return Err(SendError(value))
+    }
+    // Notify while holding the lock so that the descriptor is not closed meanwhile.
+    let one=1_u64;
+    unsafe{libc::write(self.shared.fd, &one as *const u64 as *const libc::c_void, 8)};
+    Ok(())
+  }
+
+  /// Close the channel. Values which have not been delivered yet are dropped and the following `send`
+  ///  fails. The handler is dropped and the `eventfd` is removed from the main event loop of q and closed.
+  ///  This is not necessary if every sender is dropped.
+  /// # Note
+  /// This function must be called on the main thread of q.
+  pub fn close(&self){
+    let mut queue=self.shared.queue.lock().unwrap();
+    if queue.take().is_some(){
+      unregister_fd(self.shared.fd, true);
+    }
+  }
+
+  /// Check if the channel is closed.
+  pub fn is_closed(&self) -> bool{
+    self.shared.queue.lock().unwrap().is_none()
+  }
+}
+
+#[cfg(target_os="linux")]
+impl<T> Clone for MainThreadSender<T>{
+  fn clone(&self) -> Self{
+    self.shared.senders.fetch_add(1, Ordering::SeqCst);
+    MainThreadSender{shared: Arc::clone(&self.shared)}
+  }
+}
+
+#[cfg(target_os="linux")]
+impl<T> Drop for MainThreadSender<T>{
+  /// Wake up the handler to close the channel if this is the last sender.
+  fn drop(&mut self){
+    let queue=self.shared.queue.lock().unwrap();
+    if self.shared.senders.fetch_sub(1, Ordering::SeqCst) == 1 && queue.is_some(){
+      let one=1_u64;
+      unsafe{libc::write(self.shared.fd, &one as *const u64 as *const libc::c_void, 8)};
+    }
+  }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                             Registration                             //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Hook a file descriptor to the main event loop of q by `sd1` and call `handler` with the descriptor
+///  every time it gets ready to read. A q object returned by the closure is passed back to q as the
+///  result of the callback. If a closure is already registered for the descriptor, it is replaced.
+///  As `sd1` does, a negative descriptor switches the descriptor to non-blocking and the closure is
+///  registered for its absolute value.
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+/// use std::os::unix::io::RawFd;
+///
+/// #[no_mangle]
+/// pub extern "C" fn open_well(_: K) -> K{
+///   let mut fds: [RawFd; 2]=[0; 2];
+///   if unsafe{libc::pipe(fds.as_mut_ptr())} == -1{
+///     return KError::new("failed to open pipe").to_q();
+///   }
+///   // State carried by the closure.
+///   let mut drawn=0;
+///   let registered=register_fd(fds[0], move |fd| {
+///     let mut buffer=[0_u8; 64];
+///     let length=unsafe{libc::read(fd, buffer.as_mut_ptr() as *mut libc::c_void, buffer.len())};
+///     drawn+=length.max(0);
+///     q!("{.well.drawn: x}", drawn as J).ok()
+///   });
+///   match registered{
+///     // Read end and write end.
+///     Ok(_) => fds.to_vec().into_q().into_raw(),
+///     Err(error) => {
+///       unsafe{libc::close(fds[0])};
+///       unsafe{libc::close(fds[1])};
+///       error.to_q()
+///     }
+///   }
+/// }
+///
+/// #[no_mangle]
+/// pub extern "C" fn pour_well(well: K, water: K) -> K{
+///   let fd=well.as_mut_slice::<I>()[1];
+///   let water=water.as_mut_slice::<G>();
+///   unsafe{ki(libc::write(fd, water.as_ptr() as *const libc::c_void, water.len()) as I)}
+/// }
+/// ```
+/// ```q
+/// q)open_well: LIBPATH_ (`open_well; 1);
+/// q)pour_well: LIBPATH_ (`pour_well; 2);
+/// q)well: open_well[]
+/// q)pour_well[well; "water"]
+/// 5i
+/// q)// On the next loop
+/// q).well.drawn
+/// 5
+/// ```
+/// # Note
+/// This function must be called on the main thread of q.
+pub fn register_fd<F>(fd: RawFd, handler: F) -> Result<(), KError> where F: FnMut(RawFd) -> Option<KOwned> + 'static{
+  let key=fd.abs();
+  let previous=HANDLERS.with(|handlers| handlers.borrow_mut().insert(key, Some(Box::new(handler))));
+  if previous.is_some(){
+    // Already hooked by `sd1`.
+    return Ok(());
+  }
+  // The descriptor returned by `sd1` is released here.
+  match unsafe{KOwned::from_result(sd1(fd, trampoline))}{
+    Ok(result) if result.get_type() != qtype::NULL => Ok(()),
+    _ => {
+      HANDLERS.with(|handlers| handlers.borrow_mut().remove(&key));
+      Err(KError::new("failed to hook"))
+    }
+  }
+}
+
+/// Remove a file descriptor from the main event loop of q by `sd0x` and drop the closure registered for it.
+///  The descriptor is also closed if `close` is `true`. Return `false` if no closure is registered for the
+///  descriptor.
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+///
+/// #[no_mangle]
+/// pub extern "C" fn close_well(well: K) -> K{
+///   let fds=well.as_mut_slice::<I>();
+///   // The read end is closed with the registration.
+///   let unregistered=unregister_fd(fds[0], true);
+///   if unregistered{
+///     unsafe{libc::close(fds[1])};
+///   }
+///   unsafe{kb(unregistered as I)}
+/// }
+/// ```
+/// ```q
+/// q)close_well: LIBPATH_ (`close_well; 1);
+/// q)close_well[well]
+/// 1b
+/// q)close_well[well]
+/// 0b
+/// ```
+/// # Note
+/// This function must be called on the main thread of q.
+pub fn unregister_fd(fd: RawFd, close: bool) -> bool{
+  let key=fd.abs();
+  match HANDLERS.with(|handlers| handlers.borrow_mut().remove(&key)){
+    Some(_) => {
+      unsafe{sd0x(key, close as I)};
+      true
+    },
+    None => false
+  }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                              Utility                                 //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Callback passed to `sd1` dispatching an event to the closure registered for the descriptor.
+extern "C" fn trampoline(fd: I) -> K{
+  // Take the closure out of the registry not to hold the borrow while it runs.
+  let handler=HANDLERS.with(|handlers| handlers.borrow_mut().get_mut(&fd).and_then(Option::take));
+  let mut handler=match handler{
+    Some(handler) => handler,
+    None => return KNULL!()
+  };
+  let result=handler(fd);
+  HANDLERS.with(|handlers| {
+    // Put back unless the closure was unregistered or replaced while running.
+    if let Some(slot @ None)=handlers.borrow_mut().get_mut(&fd){
+      *slot=Some(handler);
+    }
+  });
+  result.map_or(KNULL!(), KOwned::into_raw)
+}
@@ -0,0 +1,343 @@
+//! This module provides a registry of functions exported to q. Functions registered with
+//!  [`export`](../macro.export.html) are collected at compile time and returned to q at once by
+//!  `kdb_init` defined by [`export_init`](../macro.export_init.html) so that q code can load a whole library with a single `2:` call
+//!  instead of loading each function one by one.
+//!
+//! Metadata of the registered functions is also embedded in a dedicated section of the shared library
+//!  (see [`METADATA_SECTION`](constant.METADATA_SECTION.html)) so that a loader script can be generated
+//!  from a built library without running q. See `kdb-c-api-gen`.
+//!
+//! # Example
+//! ```no_run
+//! use kdb_c_api::*;
+//!
+//! #[no_mangle]
+//! pub extern "C" fn create_bool(_: K) -> K{
+//!   unsafe{kb(1)}
+//! }
+//! export!(".capi", create_bool, 1, "Example of `kb`.");
+//!
+//! // Once in the shared library.
+//! export_init!();
+//! ```
+//! ```q
+//! q)exports: `libc_api_examples 2: (`kdb_init; 1)
+//! q)key[exports] set' value exports;
+//! q).capi.create_bool[]
+//! 1b
+//! ```
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Load Libraries                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use super::*;
+use linkme::distributed_slice;
+
+#[doc(hidden)]
+pub use linkme;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                                Macros                                //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Register a function taking `K` objects to be returned by `kdb_init` defined by [`export_init`](macro.export_init.html).
+///  The function must be exported with `#[no_mangle]` so that it can be also loaded by `2:`. Arguments are:
+/// - namespace (optional): Namespace where the function is placed, e.g., `".capi"`.
+/// - function: Function to export.
+/// - arity: Number of arguments of the function. Arity over 8 fails the compilation.
+/// - doc (optional): Description of the function.
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+///
+/// #[no_mangle]
+/// pub extern "C" fn create_symbol(_: K) -> K{
+///   unsafe{ks(str_to_S!("symbolism"))}
+/// }
+/// export!(create_symbol, 1);
+///
+/// #[no_mangle]
+/// pub extern "C" fn rust_parse(dollar: K, type_and_text: K) -> K{
+///   unsafe{dot(dollar, type_and_text)}
+/// }
+/// export!(".capi", rust_parse, 2, "Example of `dot`.");
+/// ```
+#[macro_export]
+macro_rules! export {
+  ($namespace: literal, $function: ident, $arity: expr, $doc: expr) => {
+    const _: () = {
+      const NAME: &str=stringify!($function);
+      const LENGTH: usize=$crate::export::metadata_length(NAME, $namespace, $doc);
+      #[cfg_attr(any(target_os="linux", target_os="android", target_os="freebsd"), link_section="kdb_c_api_meta")]
+      #[cfg_attr(target_os="macos", link_section="__DATA,kdb_c_api_meta")]
+      #[cfg_attr(windows, link_section=".kdbmeta")]
+      #[used]
+      static METADATA: [u8; LENGTH]=$crate::export::encode_metadata::<LENGTH>(NAME, $namespace, $arity, $doc);
+      #[$crate::export::linkme::distributed_slice($crate::export::EXPORTS)]
+      #[linkme(crate=$crate::export::linkme)]
+      static EXPORT: $crate::export::Export=$crate::export::Export{
+        name: NAME,
+        namespace: $namespace,
+        arity: $arity,
+        doc: $doc,
+        function: $function as *const $crate::V
+      };
+    };
+  };
+  ($namespace: literal, $function: ident, $arity: expr) => {
+    $crate::export!($namespace, $function, $arity, "");
+  };
+  ($function: ident, $arity: expr, $doc: expr) => {
+    $crate::export!("", $function, $arity, $doc);
+  };
+  ($function: ident, $arity: expr) => {
+    $crate::export!("", $function, $arity, "");
+  };
+}
+
+/// Define the entry point `kdb_init` of a shared library returning a dictionary from the qualified names of
+///  the functions registered by [`export`](macro.export.html) to q functions. Invoke this macro once in the
+///  crate built as the shared library; it is not defined by `kdb_c_api` itself so that other binaries linking
+///  this crate do not export the symbol.
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+///
+/// export_init!();
+/// ```
+/// ```q
+/// q)exports: `libc_api_examples 2: (`kdb_init; 1)
+/// q)key[exports] set' value exports;
+/// q).capi.rust_parse[$; ("J"; "42")]
+/// 42
+/// ```
+#[macro_export]
+macro_rules! export_init {
+  () => {
+    #[no_mangle]
+    pub extern "C" fn kdb_init(_: $crate::K) -> $crate::K{
+      $crate::export::exports_to_q()
+    }
+  };
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                               Structs                                //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Information of a function registered by [`export`](../macro.export.html).
+#[derive(Debug)]
+pub struct Export{
+  /// Symbol of the function in the shared library.
+  pub name: &'static str,
+  /// Namespace where the function is placed. Empty for the root namespace.
+  pub namespace: &'static str,
+  /// Number of arguments.
+  pub arity: J,
+  /// Description of the function.
+  pub doc: &'static str,
+  /// Pointer to the function.
+  pub function: *const V
+}
+
+// Only read after registration.
+unsafe impl Sync for Export{}
+
+/// Metadata of an exported function read from the section of a shared library.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Metadata{
+  /// Symbol of the function in the shared library.
+  pub name: String,
+  /// Namespace where the function is placed. Empty for the root namespace.
+  pub namespace: String,
+  /// Number of arguments.
+  pub arity: J,
+  /// Description of the function.
+  pub doc: String
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                          Global Variables                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Functions registered by [`export`](../macro.export.html).
+#[distributed_slice]
+pub static EXPORTS: [Export];
+
+/// Name of the section where metadata of exported functions is embedded in ELF format.
+///  `__DATA,kdb_c_api_meta` is used for Mach-O and `.kdbmeta` for PE.
+pub const METADATA_SECTION: &str="kdb_c_api_meta";
+
+/// Version of the metadata layout. A record of the metadata is:
+/// - version (1 byte)
+/// - arity (1 byte)
+/// - length of name (2 bytes, little endian)
+/// - length of namespace (2 bytes, little endian)
+/// - length of doc (4 bytes, little endian)
+/// - name, namespace and doc
+/// 
+/// Records may be separated by zero bytes of alignment.
+pub const METADATA_VERSION: u8=1;
+
+/// Length of a header of a metadata record.
+const METADATA_HEADER_LENGTH: usize=10;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Implementation                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+impl Export{
+  /// Name of the function on q side, i.e., the name prefixed by the namespace.
+  pub fn qualified_name(&self) -> String{
+    if self.namespace.is_empty(){
+      self.name.to_string()
+    }
+    else{
+      format!("{}.{}", self.namespace, self.name)
+    }
+  }
+}
+
+impl Metadata{
+  /// Name of the function on q side, i.e., the name prefixed by the namespace.
+  pub fn qualified_name(&self) -> String{
+    if self.namespace.is_empty(){
+      self.name.clone()
+    }
+    else{
+      format!("{}.{}", self.namespace, self.name)
+    }
+  }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                              Utility                                 //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Get functions registered by [`export`](../macro.export.html) sorted by the qualified name.
+pub fn exports() -> Vec<&'static Export>{
+  let mut exports=EXPORTS.iter().collect::<Vec<_>>();
+  exports.sort_by_key(|export| export.qualified_name());
+  exports
+}
+
+/// Build a dictionary from the qualified names of the functions registered by [`export`](../macro.export.html)
+///  to q functions. Used by `kdb_init` defined by [`export_init`](../macro.export_init.html).
+/// # Note
+/// This function must be called on the main thread of q.
+pub fn exports_to_q() -> K{
+  unsafe{
+    let mut keys=ktn(qtype::SYMBOL as I, 0);
+    let mut values=knk(0);
+    for export in exports(){
+      js(&mut keys, ss(str_to_S!(export.qualified_name())));
+      jk(&mut values, dl(export.function, export.arity));
+    }
+    xD(keys, values)
+  }
+}
+
+//%% Metadata %%//vvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvv/
+
+/// Length of a metadata record. Used by [`export`](../macro.export.html).
+#[doc(hidden)]
+pub const fn metadata_length(name: &str, namespace: &str, doc: &str) -> usize{
+  METADATA_HEADER_LENGTH + name.len() + namespace.len() + doc.len()
+}
+
+/// Encode a metadata record at compile time. Used by [`export`](../macro.export.html). Arity
+///  over [`MAX_ARITY`](../constant.MAX_ARITY.html) fails the compilation.
+#[doc(hidden)]
+pub const fn encode_metadata<const N: usize>(name: &str, namespace: &str, arity: J, doc: &str) -> [u8; N]{
+  if arity < 0 || arity > MAX_ARITY as J{
+    panic!("arity of an exported function must be between 0 and 8");
+  }
+  let mut record=[0_u8; N];
+  record[0]=METADATA_VERSION;
+  record[1]=arity as u8;
+  let name_length=(name.len() as u16).to_le_bytes();
+  record[2]=name_length[0];
+  record[3]=name_length[1];
+  let namespace_length=(namespace.len() as u16).to_le_bytes();
+  record[4]=namespace_length[0];
+  record[5]=namespace_length[1];
+  let doc_length=(doc.len() as u32).to_le_bytes();
+  let mut i=0;
+  while i < 4{
+    record[6+i]=doc_length[i];
+    i+=1;
+  }
+  let mut offset=METADATA_HEADER_LENGTH;
+  let mut i=0;
+  while i < name.len(){
+    record[offset+i]=name.as_bytes()[i];
+    i+=1;
+  }
+  offset+=name.len();
+  let mut i=0;
+  while i < namespace.len(){
+    record[offset+i]=namespace.as_bytes()[i];
+    i+=1;
+  }
+  offset+=namespace.len();
+  let mut i=0;
+  while i < doc.len(){
+    record[offset+i]=doc.as_bytes()[i];
+    i+=1;
+  }
+  record
+}
+
+/// Decode metadata records embedded in the section [`METADATA_SECTION`](constant.METADATA_SECTION.html).
+/// # Example
+/// ```
+/// use kdb_c_api::export::*;
+/// 
+/// const LENGTH: usize=metadata_length("walk", ".capi", "Walk.");
+/// let record=encode_metadata::<LENGTH>("walk", ".capi", 2, "Walk.");
+/// // Zero bytes of alignment between records are skipped.
+/// let section=[&record[..], &[0, 0], &record[..]].concat();
+/// let metadata=parse_metadata(&section).unwrap();
+/// assert_eq!(metadata.len(), 2);
+/// assert_eq!(metadata[0].qualified_name(), ".capi.walk");
+/// assert_eq!(metadata[0].arity, 2);
+/// assert_eq!(metadata[0].doc, "Walk.");
+/// ```
+pub fn parse_metadata(section: &[u8]) -> Result<Vec<Metadata>, &'static str>{
+  let mut metadata=Vec::new();
+  let mut cursor=0;
+  while cursor < section.len(){
+    match section[cursor]{
+      0 => {
+        // Alignment
+        cursor+=1;
+        continue;
+      },
+      METADATA_VERSION => (),
+      _ => return Err("unknown metadata version")
+    }
+    if section.len() < cursor + METADATA_HEADER_LENGTH{
+      return Err("truncated metadata");
+    }
+    let header=&section[cursor..cursor+METADATA_HEADER_LENGTH];
+    let arity=header[1] as J;
+    let name_length=u16::from_le_bytes([header[2], header[3]]) as usize;
+    let namespace_length=u16::from_le_bytes([header[4], header[5]]) as usize;
+    let doc_length=u32::from_le_bytes([header[6], header[7], header[8], header[9]]) as usize;
+    cursor+=METADATA_HEADER_LENGTH;
+    if section.len() < cursor + name_length + namespace_length + doc_length{
+      return Err("truncated metadata");
+    }
+    let mut next_string=|length: usize| -> Result<String, &'static str>{
+      let string=str::from_utf8(&section[cursor..cursor+length]).map_err(|_| "invalid utf8 in metadata")?;
+      cursor+=length;
+      Ok(string.to_string())
+    };
+    let name=next_string(name_length)?;
+    let namespace=next_string(namespace_length)?;
+    let doc=next_string(doc_length)?;
+    metadata.push(Metadata{name, namespace, arity, doc});
+  }
+  Ok(metadata)
+}
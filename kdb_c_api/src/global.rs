@@ -0,0 +1,171 @@
+//! This module provides access to global variables of the q process which loads the shared library.
+//!  A name of a global variable is given as a string with or without a leading backtick, e.g., `".ns.var"`
+//!  or ``"`.ns.var"``.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Load Libraries                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use super::*;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Global Access                             //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Get a value of a global variable converted into a Rust type. An undefined variable is returned
+///  as q error with the name.
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+///
+/// #[no_mangle]
+/// pub extern "C" fn read_scroll(_: K) -> K{
+///   match get_global::<String>(".scroll.verse"){
+///     Ok(verse) => {
+///       println!("{}", verse);
+///       unsafe{kj(verse.len() as J)}
+///     },
+///     Err(error) => error.to_q()
+///   }
+/// }
+/// ```
+/// ```q
+/// q).scroll.verse: "In the beginning";
+/// q)read_scroll: LIBPATH_ (`read_scroll; 1);
+/// q)read_scroll[]
+/// In the beginning
+/// 16
+/// ```
+/// # Note
+/// This function must be called on the main thread of q.
+pub fn get_global<T: FromQ>(name: &str) -> Result<T, KError>{
+  q!("{get x}", global_name(name)?)
+}
+
+/// Assign a value to a global variable. The variable is created if it does not exist.
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+///
+/// #[no_mangle]
+/// pub extern "C" fn write_scroll(verse: K) -> K{
+///   match set_global(".scroll.verse", unsafe{KOwned::from_borrowed(verse)}){
+///     Ok(_) => KNULL!(),
+///     Err(error) => error.to_q()
+///   }
+/// }
+/// ```
+/// ```q
+/// q)write_scroll: LIBPATH_ (`write_scroll; 1);
+/// q)write_scroll["Let there be light"]
+/// q).scroll.verse
+/// "Let there be light"
+/// ```
+/// # Note
+/// This function must be called on the main thread of q.
+pub fn set_global<T: IntoQ>(name: &str, value: T) -> Result<(), KError>{
+  q!("{x set y}", global_name(name)?, value)
+}
+
+/// Upsert records to a global table (``name upsert value``). q error, e.g., `'type` or `'mismatch`,
+///  is returned if the records do not fit the table.
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+///
+/// #[no_mangle]
+/// pub extern "C" fn record_census(records: K) -> K{
+///   match upsert_global("census", unsafe{KOwned::from_borrowed(records)}){
+///     Ok(_) => KNULL!(),
+///     Err(error) => error.to_q()
+///   }
+/// }
+/// ```
+/// ```q
+/// q)census: ([] tribe: `symbol$(); men: `long$());
+/// q)record_census: LIBPATH_ (`record_census; 1);
+/// q)record_census[(`Reuben; 46500)]
+/// q)census
+/// tribe  men
+/// ------------
+/// Reuben 46500
+/// ```
+/// # Note
+/// This function must be called on the main thread of q.
+pub fn upsert_global<T: IntoQ>(name: &str, value: T) -> Result<(), KError>{
+  q!("{x upsert y}", global_name(name)?, value)
+}
+
+/// Check if a global variable is defined.
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+///
+/// #[no_mangle]
+/// pub extern "C" fn is_written(name: K) -> K{
+///   match name.get_symbol(){
+///     Ok(name) => unsafe{kb(global_exists(name) as I)},
+///     Err(error) => unsafe{krr(null_terminated_str_to_const_S(error))}
+///   }
+/// }
+/// ```
+/// ```q
+/// q)is_written: LIBPATH_ (`is_written; 1);
+/// q)is_written[`.scroll.verse]
+/// 0b
+/// q).scroll.verse: "In the beginning";
+/// q)is_written[`.scroll.verse]
+/// 1b
+/// ```
+/// # Note
+/// This function must be called on the main thread of q.
+pub fn global_exists(name: &str) -> bool{
+  match global_name(name){
+    Ok(name) => q!("{@[{get x; 1b}; x; 0b]}", name).unwrap_or(false),
+    Err(_) => false
+  }
+}
+
+/// List names defined in a namespace (``key `.ns``) excluding the null symbol. The root namespace is
+///  given as `"."`. q error is returned if the name is not a namespace.
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+///
+/// #[no_mangle]
+/// pub extern "C" fn open_scroll(namespace: K) -> K{
+///   let list=namespace.get_symbol().map_err(KError::from).and_then(list_namespace);
+///   match list{
+///     Ok(names) => names.into_iter().map(Symbol).collect::<Vec<_>>().into_q().into_raw(),
+///     Err(error) => error.to_q()
+///   }
+/// }
+/// ```
+/// ```q
+/// q).scroll.verse: "In the beginning";
+/// q).scroll.chapter: 1;
+/// q)open_scroll: LIBPATH_ (`open_scroll; 1);
+/// q)open_scroll[`.scroll]
+/// `verse`chapter
+/// ```
+/// # Note
+/// This function must be called on the main thread of q.
+pub fn list_namespace(namespace: &str) -> Result<Vec<String>, KError>{
+  q!("{$[99h = type namespace: get x; except[key namespace; `]; '\"not a namespace\"]}", global_name(namespace)?)
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                              Utility                                 //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Convert a name of a global variable into a symbol. A file handle (name starting with `:`)
+///  is rejected not to read a file by `get`.
+fn global_name(name: &str) -> Result<Symbol, KError>{
+  let name=name.strip_prefix('`').unwrap_or(name);
+  if name.is_empty() || name.starts_with(':') || name.contains('\0'){
+    Err(KError::new("invalid global name"))
+  }
+  else{
+    Ok(Symbol(name.to_string()))
+  }
+}
@@ -0,0 +1,321 @@
+//! This module provides a connection to a q process in pure Rust, a replacement of `khpunc`, `k` and `kclose`
+//!  of `c.o` which are not available to a shared library. Enabled by the feature `client`.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Load Libraries                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use super::*;
+use crate::KError;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                               Structs                                //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Error of connecting to a q process. [`code`](enum.ConnectError.html#method.code) gives the value
+///  returned by `khpun` for the error.
+/// # Example
+/// ```
+/// use kdb_c_api::ipc::*;
+/// use std::io::Read;
+/// use std::net::TcpListener;
+/// use std::time::Duration;
+///
+/// // Stand-in server rejecting any user.
+/// let listener=TcpListener::bind("127.0.0.1:0").unwrap();
+/// let port=listener.local_addr().unwrap().port();
+/// std::thread::spawn(move || {
+///   let (mut stream, _)=listener.accept().unwrap();
+///   let mut handshake=[0_u8; 64];
+///   stream.read(&mut handshake).unwrap();
+///   // Closed without a reply.
+/// });
+/// let error=Connection::connect("127.0.0.1", port, "intruder:guess", None).unwrap_err();
+/// assert_eq!(error, ConnectError::Authentication);
+/// assert_eq!(error.code(), 0);
+///
+/// // Stand-in server never replying.
+/// let listener=TcpListener::bind("127.0.0.1:0").unwrap();
+/// let port=listener.local_addr().unwrap().port();
+/// let error=Connection::connect("127.0.0.1", port, "", Some(Duration::from_millis(100))).unwrap_err();
+/// assert_eq!(error.code(), -2);
+///
+/// // Nobody listens.
+/// drop(listener);
+/// assert_eq!(Connection::connect("127.0.0.1", port, "", None).unwrap_err().code(), -1);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConnectError{
+  /// Credential is rejected by the server. Code `0`.
+  Authentication,
+  /// Host is not found or the connection is refused. Code `-1`.
+  Connection(String),
+  /// Connection or handshake is not completed within the timeout. Code `-2`.
+  Timeout
+}
+
+/// Connection to a q process. The connection is closed when it is dropped.
+/// # Example
+/// ```
+/// use kdb_c_api::ipc::*;
+/// use std::io::{Read, Write};
+/// use std::net::TcpListener;
+///
+/// // Stand-in server answering `til 3` for any query.
+/// let listener=TcpListener::bind("127.0.0.1:0").unwrap();
+/// let port=listener.local_addr().unwrap().port();
+/// std::thread::spawn(move || {
+///   let (mut stream, _)=listener.accept().unwrap();
+///   // Credential, capability and a null character.
+///   let mut byte=[1_u8];
+///   while byte[0] != 0{
+///     stream.read_exact(&mut byte).unwrap();
+///   }
+///   stream.write_all(&[PROTOCOL_VERSION]).unwrap();
+///   let mut reader=MessageReader::new(Limits::default());
+///   while let Ok(Some(message))=reader.read(&mut stream){
+///     if let Ok((MessageType::Sync, _))=decode_message(&message, &Limits::default()){
+///       stream.write_all(&encode_message(&QValue::from(vec![0_i64, 1, 2]), MessageType::Response, Endian::Little).unwrap()).unwrap();
+///     }
+///   }
+/// });
+/// let mut connection=Connection::connect("127.0.0.1", port, "kdb:rust", None).unwrap();
+/// assert_eq!(connection.version(), PROTOCOL_VERSION);
+/// connection.query_async(&QValue::from("a: 1")).unwrap();
+/// assert_eq!(connection.query_sync(&QValue::from("til 3")).unwrap(), QValue::from(vec![0_i64, 1, 2]));
+/// ```
+#[derive(Debug)]
+pub struct Connection{
+  stream: TcpStream,
+  /// Protocol version negotiated in the handshake.
+  version: u8,
+  /// Compress a large message as q does for a remote host.
+  compress: bool,
+  reader: MessageReader,
+  /// Messages received while waiting for a response.
+  pending: VecDeque<(MessageType, QValue)>,
+  /// Set when a message is abandoned half way by a timeout or an I/O error. The stream is out of sync after
+  ///  that, e.g., a late response would be taken for the response of the next query.
+  broken: bool
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Implementation                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+//%% ConnectError %%//vvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvv/
+
+impl ConnectError{
+  /// Value returned by `khpun` for the error.
+  pub fn code(&self) -> i32{
+    match self{
+      ConnectError::Authentication => 0,
+      ConnectError::Connection(_) => -1,
+      ConnectError::Timeout => -2
+    }
+  }
+}
+
+impl fmt::Display for ConnectError{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+    match self{
+      ConnectError::Authentication => write!(f, "access"),
+      ConnectError::Connection(reason) => write!(f, "hop. {}", reason),
+      ConnectError::Timeout => write!(f, "timeout")
+    }
+  }
+}
+
+impl std::error::Error for ConnectError{}
+
+impl From<ConnectError> for KError{
+  fn from(error: ConnectError) -> Self{
+    KError::new(error.to_string())
+  }
+}
+
+//%% Connection %%//vvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvv/
+
+impl Connection{
+  /// Connect to a q process like `khpun`. `credential` is `"user:password"`. `timeout` bounds the connection
+  ///  and the handshake; queries are not timed out unless [`set_timeout`](struct.Connection.html#method.set_timeout)
+  ///  is called.
+  pub fn connect(host: &str, port: u16, credential: &str, timeout: Option<Duration>) -> Result<Self, ConnectError>{
+    Connection::connect_with_capability(host, port, credential, timeout, PROTOCOL_VERSION)
+  }
+
+  /// Connect to a q process requesting a protocol version by the capability byte like `khpunc`. Pass
+  ///  [`LARGE_MESSAGE_VERSION`](constant.LARGE_MESSAGE_VERSION.html) to exchange messages over 2GB.
+  pub fn connect_with_capability(host: &str, port: u16, credential: &str, timeout: Option<Duration>, capability: u8) -> Result<Self, ConnectError>{
+    let mut stream=open(host, port, timeout)?;
+    let version=handshake(&mut stream, credential, capability, timeout)?;
+    let compress=stream.peer_addr().map(|address| !address.ip().is_loopback()).unwrap_or(false);
+    stream.set_read_timeout(None).and_then(|_| stream.set_write_timeout(None)).map_err(|error| ConnectError::Connection(error.to_string()))?;
+    Ok(Connection{stream, version, compress, reader: MessageReader::new(Limits::for_version(version)), pending: VecDeque::new(), broken: false})
+  }
+
+  /// Protocol version negotiated with the q process.
+  pub fn version(&self) -> u8{
+    self.version
+  }
+
+  /// Check if the connection is out of sync after a timeout or an I/O error. Every query fails with
+  ///  `'broken` once it is broken; connect again to continue.
+  pub fn is_broken(&self) -> bool{
+    self.broken
+  }
+
+  /// Set the timeout of sending a query and receiving a message. `'timeout` is returned by a query not
+  ///  completed within the timeout. `None` waits forever. The connection is broken by a timeout because
+  ///  the response may still arrive (see [`is_broken`](struct.Connection.html#method.is_broken)).
+  /// # Example
+  /// ```
+  /// use kdb_c_api::ipc::*;
+  /// use std::io::{Read, Write};
+  /// use std::net::TcpListener;
+  /// use std::time::Duration;
+  ///
+  /// // Stand-in server answering a query too late.
+  /// let listener=TcpListener::bind("127.0.0.1:0").unwrap();
+  /// let port=listener.local_addr().unwrap().port();
+  /// std::thread::spawn(move || {
+  ///   let (mut stream, _)=listener.accept().unwrap();
+  ///   let mut byte=[1_u8];
+  ///   while byte[0] != 0{
+  ///     stream.read_exact(&mut byte).unwrap();
+  ///   }
+  ///   stream.write_all(&[PROTOCOL_VERSION]).unwrap();
+  ///   let mut reader=MessageReader::new(Limits::default());
+  ///   while let Ok(Some(_))=reader.read(&mut stream){
+  ///     std::thread::sleep(Duration::from_millis(300));
+  ///     stream.write_all(&encode_message(&QValue::Long(42), MessageType::Response, Endian::Little).unwrap()).unwrap();
+  ///   }
+  /// });
+  /// let mut connection=Connection::connect("127.0.0.1", port, "", None).unwrap();
+  /// connection.set_timeout(Some(Duration::from_millis(100))).unwrap();
+  /// assert_eq!(connection.query_sync(&QValue::from("6*7")).unwrap_err().message(), "timeout");
+  /// assert!(connection.is_broken());
+  /// // The late response is not taken for the response of the next query.
+  /// std::thread::sleep(Duration::from_millis(400));
+  /// assert!(connection.query_sync(&QValue::from("1+1")).unwrap_err().message().starts_with("broken"));
+  /// ```
+  pub fn set_timeout(&self, timeout: Option<Duration>) -> Result<(), KError>{
+    self.stream.set_read_timeout(timeout).and_then(|_| self.stream.set_write_timeout(timeout)).map_err(|error| KError::new(format!("failed to set timeout: {}", error)))
+  }
+
+  /// Send a synchronous query and wait for the response. An error returned by the q process is returned as
+  ///  `KError`. Messages received before the response are kept for [`receive`](struct.Connection.html#method.receive).
+  pub fn query_sync(&mut self, query: &QValue) -> Result<QValue, KError>{
+    self.check()?;
+    self.send(query, MessageType::Sync)?;
+    loop{
+      match self.read()?{
+        (MessageType::Response, QValue::Error(error)) => return Err(KError::new(error)),
+        (MessageType::Response, response) => return Ok(response),
+        message => self.pending.push_back(message)
+      }
+    }
+  }
+
+  /// Send an asynchronous query.
+  pub fn query_async(&mut self, query: &QValue) -> Result<(), KError>{
+    self.check()?;
+    self.send(query, MessageType::Async)
+  }
+
+  /// Receive a message sent by the q process, e.g., by `neg[.z.w]`.
+  pub fn receive(&mut self) -> Result<(MessageType, QValue), KError>{
+    self.check()?;
+    match self.pending.pop_front(){
+      Some(message) => Ok(message),
+      None => self.read()
+    }
+  }
+
+  /// Fail if the connection is broken.
+  fn check(&self) -> Result<(), KError>{
+    if self.broken {Err(KError::new("broken: connection is out of sync after a timeout or an I/O error"))} else {Ok(())}
+  }
+
+  /// Frame a query and write it to the stream.
+  fn send(&mut self, query: &QValue, message_type: MessageType) -> Result<(), KError>{
+    let mut message=encode_message_with_version(query, message_type, Endian::native(), self.version)?;
+    if self.compress && message.len() > COMPRESSION_THRESHOLD{
+      if let Some(compressed)=compress(&message){
+        message=compressed;
+      }
+    }
+    let mut writer=MessageWriter::new();
+    writer.push(message);
+    // Blocking stream would block only when timed out.
+    match writer.write(&mut self.stream){
+      Ok(true) => Ok(()),
+      result => {
+        self.broken=true;
+        result.and(Err(KError::new("timeout")))
+      }
+    }
+  }
+
+  /// Read and decode the next message from the stream.
+  fn read(&mut self) -> Result<(MessageType, QValue), KError>{
+    match self.reader.read(&mut self.stream){
+      Ok(Some(message)) => decode_message(&message, &Limits::for_version(self.version)),
+      result => {
+        self.broken=true;
+        result.and(Err(KError::new("timeout")))
+      }
+    }
+  }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                              Utility                                 //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Connect to the first reachable address of a host.
+fn open(host: &str, port: u16, timeout: Option<Duration>) -> Result<TcpStream, ConnectError>{
+  let addresses=(host, port).to_socket_addrs().map_err(|error| ConnectError::Connection(error.to_string()))?;
+  let mut last_error=ConnectError::Connection(format!("no address for {}", host));
+  for address in addresses{
+    let result=match timeout{
+      Some(timeout) => TcpStream::connect_timeout(&address, timeout),
+      None => TcpStream::connect(address)
+    };
+    match result{
+      Ok(stream) => return Ok(stream),
+      Err(error) => last_error=connect_error(error)
+    }
+  }
+  Err(last_error)
+}
+
+/// Send the credential and the capability byte followed by a null character and receive the protocol
+///  version. The server closes the connection if the credential is rejected.
+fn handshake(stream: &mut TcpStream, credential: &str, capability: u8, timeout: Option<Duration>) -> Result<u8, ConnectError>{
+  stream.set_read_timeout(timeout).and_then(|_| stream.set_write_timeout(timeout)).map_err(connect_error)?;
+  let mut request=credential.as_bytes().to_vec();
+  request.push(capability);
+  request.push(0);
+  stream.write_all(&request).map_err(connect_error)?;
+  let mut version=[0_u8];
+  match stream.read(&mut version){
+    Ok(0) => Err(ConnectError::Authentication),
+    Ok(_) => Ok(version[0]),
+    Err(error) if error.kind() == io::ErrorKind::ConnectionReset => Err(ConnectError::Authentication),
+    Err(error) => Err(connect_error(error))
+  }
+}
+
+/// Classify an I/O error of connection.
+fn connect_error(error: io::Error) -> ConnectError{
+  match error.kind(){
+    io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => ConnectError::Timeout,
+    _ => ConnectError::Connection(error.to_string())
+  }
+}
@@ -0,0 +1,205 @@
+//! This module provides compression of IPC messages used by q for a remote connection and `-18!`.
+//!  A compressed message has `1` at the third byte of the header, followed by the uncompressed length
+//!  and blocks of eight tokens led by a control byte. Each bit of the control byte tells whether the
+//!  token is a literal byte or a back reference found through a hash table of byte pairs.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Load Libraries                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use super::*;
+use super::decode::ReadScalar;
+use super::encode::Scalar;
+use crate::KError;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                          Global Variables                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Offset of the first control byte in a compressed message. The uncompressed length is stored before it.
+const DATA_OFFSET: usize=12;
+
+/// Length of a message over which q compresses the message sent to a remote process.
+pub const COMPRESSION_THRESHOLD: usize=2000;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                             Compression                              //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Compress an IPC message in the same way as q. `None` is returned if the message is not compressed to
+///  less than half of its length, in which case q sends the message as it is. Note that q compresses only
+///  a message longer than [`COMPRESSION_THRESHOLD`](constant.COMPRESSION_THRESHOLD.html).
+/// # Example
+/// ```
+/// use kdb_c_api::ipc::*;
+///
+/// // q)-18!1000#`kdb
+/// let message=encode_message(&QValue::from(vec![QValue::Symbol("kdb".to_string()); 1000]), MessageType::Async, Endian::Little).unwrap();
+/// let compressed=compress(&message).unwrap();
+/// assert_eq!(compressed[2], 1);
+/// assert!(compressed.len() * 2 < message.len());
+/// assert_eq!(decompress(&compressed, &Limits::default()).unwrap(), message);
+/// // Not worth compressing
+/// assert_eq!(compress(&encode_message(&QValue::Long(1), MessageType::Async, Endian::Little).unwrap()), None);
+/// ```
+pub fn compress(message: &[u8]) -> Option<Vec<u8>>{
+  let length=message.len();
+  // Large message is not compressed.
+  if length < HEADER_LENGTH || length > i32::MAX as usize{
+    return None;
+  }
+  let endian=if message[0] == 0 {Endian::Big} else {Endian::Little};
+  let capacity=length / 2;
+  if capacity < DATA_OFFSET{
+    return None;
+  }
+  let mut compressed=vec![0_u8; capacity];
+  compressed[..4].copy_from_slice(&message[..4]);
+  compressed[2]=1;
+  // Last position of a byte pair keyed by XOR of the pair.
+  let mut table=[0_usize; 256];
+  // Bit of the current token in the control byte.
+  let mut bit=0_u8;
+  let mut control=0_u8;
+  let mut control_position=HEADER_LENGTH;
+  let mut destination=DATA_OFFSET;
+  let mut source=HEADER_LENGTH;
+  // Hash of a literal pair registered on the next step.
+  let mut hash=0_usize;
+  let mut pending_hash=0_usize;
+  let mut pending_position=0_usize;
+  while source < length{
+    if bit == 0{
+      if destination + 17 > capacity{
+        return None;
+      }
+      bit=1;
+      compressed[control_position]=control;
+      control_position=destination;
+      destination+=1;
+      control=0;
+    }
+    let mut literal=source + 3 > length;
+    let mut reference=0;
+    if !literal{
+      hash=(message[source] ^ message[source + 1]) as usize;
+      reference=table[hash];
+      literal=reference == 0 || message[source] != message[reference];
+    }
+    if pending_position > 0{
+      table[pending_hash]=pending_position;
+      pending_position=0;
+    }
+    if literal{
+      pending_hash=hash;
+      pending_position=source;
+      compressed[destination]=message[source];
+      destination+=1;
+      source+=1;
+    }
+    else{
+      table[hash]=source;
+      control|=bit;
+      reference+=2;
+      source+=2;
+      let start=source;
+      let end=(source + 255).min(length);
+      while message[reference] == message[source]{
+        source+=1;
+        if source >= end{
+          break;
+        }
+        reference+=1;
+      }
+      compressed[destination]=hash as u8;
+      compressed[destination + 1]=(source - start) as u8;
+      destination+=2;
+    }
+    bit=bit.wrapping_mul(2);
+  }
+  compressed[control_position]=control;
+  compressed.truncate(destination);
+  write_message_length(&mut compressed, destination as u64);
+  let mut uncompressed_length=Vec::with_capacity(4);
+  (length as i32).write(endian, &mut uncompressed_length);
+  compressed[HEADER_LENGTH..DATA_OFFSET].copy_from_slice(&uncompressed_length);
+  Some(compressed)
+}
+
+/// Decompress an IPC message compressed by q or [`compress`](fn.compress.html). A message which is not
+///  compressed is returned as it is. The uncompressed length is checked against `max_size` of `limits`
+///  before memory is allocated.
+/// # Example
+/// See the example of [`compress`](fn.compress.html).
+pub fn decompress(message: &[u8], limits: &Limits) -> Result<Vec<u8>, KError>{
+  if message.len() < HEADER_LENGTH{
+    return Err(KError::new(format!("truncated: message of {} bytes is shorter than the header", message.len())));
+  }
+  if message[2] == 0{
+    return Ok(message.to_vec());
+  }
+  if message.len() < DATA_OFFSET{
+    return Err(KError::new("truncated: compressed message has no uncompressed length"));
+  }
+  let endian=if message[0] == 0 {Endian::Big} else {Endian::Little};
+  let length=i32::read(&message[HEADER_LENGTH..DATA_OFFSET], endian);
+  if length < HEADER_LENGTH as i32{
+    return Err(KError::new(format!("header: invalid uncompressed length {}", length)));
+  }
+  let length=length as usize;
+  if length > limits.max_size{
+    return Err(KError::new(format!("size: {} bytes exceed the limit of {}", length, limits.max_size)));
+  }
+  let mut decompressed=vec![0_u8; length];
+  let truncated=|offset: usize| KError::new(format!("truncated: compressed data ends at offset {}", offset));
+  let mut table=[0_usize; 256];
+  let mut bit=0_u16;
+  let mut control=0_u8;
+  let mut source=DATA_OFFSET;
+  let mut destination=HEADER_LENGTH;
+  // Position up to which pairs are registered in the table.
+  let mut registered=HEADER_LENGTH;
+  while destination < length{
+    if bit == 0{
+      control=message.get(source).copied().ok_or_else(|| truncated(source))?;
+      source+=1;
+      bit=1;
+    }
+    let mut extra=0;
+    if control as u16 & bit != 0{
+      let (hash, count)=match message.get(source..source + 2){
+        Some(token) => (token[0], token[1] as usize),
+        None => return Err(truncated(source))
+      };
+      let reference=table[hash as usize];
+      if destination + 2 + count > length{
+        return Err(KError::new(format!("length: back reference at offset {} overruns the uncompressed length", source)));
+      }
+      // Copy one by one as the ranges may overlap.
+      for index in 0..count + 2{
+        decompressed[destination + index]=decompressed[reference + index];
+      }
+      destination+=2;
+      extra=count;
+      source+=2;
+    }
+    else{
+      decompressed[destination]=message.get(source).copied().ok_or_else(|| truncated(source))?;
+      destination+=1;
+      source+=1;
+    }
+    while registered + 1 < destination{
+      table[(decompressed[registered] ^ decompressed[registered + 1]) as usize]=registered;
+      registered+=1;
+    }
+    if control as u16 & bit != 0{
+      destination+=extra;
+      registered=destination;
+    }
+    bit=if bit == 128 {0} else {bit * 2};
+  }
+  decompressed[..HEADER_LENGTH].copy_from_slice(&message[..HEADER_LENGTH]);
+  decompressed[2]=0;
+  write_message_length(&mut decompressed, length as u64);
+  Ok(decompressed)
+}
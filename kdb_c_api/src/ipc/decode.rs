@@ -0,0 +1,477 @@
+//! This module provides deserialization of kdb+ IPC bytes into `QValue`, the counterpart of `d9`.
+//!  Unlike `d9`, malformed input is reported with the reason and the offset of the offending byte,
+//!  and the resources consumed by untrusted input are bounded by [`Limits`](struct.Limits.html).
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Load Libraries                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use super::*;
+use crate::{qtype, KOwned, KError};
+use std::convert::TryInto;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                                Traits                                //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Number read in the byte order of a message.
+pub(crate) trait ReadScalar: Copy{
+  /// Size of the number in bytes.
+  const SIZE: usize;
+  /// Read the number from bytes of `SIZE`.
+  fn read(bytes: &[u8], endian: Endian) -> Self;
+}
+
+macro_rules! impl_read_scalar {
+  ($($rust_type: ty),*) => {
+    $(
+      impl ReadScalar for $rust_type{
+        const SIZE: usize=std::mem::size_of::<$rust_type>();
+        fn read(bytes: &[u8], endian: Endian) -> Self{
+          let bytes=bytes.try_into().unwrap();
+          match endian{
+            Endian::Little => <$rust_type>::from_le_bytes(bytes),
+            Endian::Big => <$rust_type>::from_be_bytes(bytes)
+          }
+        }
+      }
+    )*
+  };
+}
+
+impl_read_scalar!(i16, i32, i64, f32, f64);
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                               Structs                                //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Bounds of resources consumed by decoding untrusted bytes. Input exceeding a limit is rejected before
+///  memory is allocated for it.
+/// # Example
+/// ```
+/// use kdb_c_api::ipc::*;
+///
+/// let limits=Limits{max_list_length: 2, ..Limits::default()};
+/// let bytes=encode(&QValue::from(vec![1_i64, 2, 3]), Endian::Little).unwrap();
+/// let error=decode(&bytes, Endian::Little, &limits).unwrap_err();
+/// assert_eq!(error.message(), "length: list of 3 elements at offset 0 exceeds the limit of 2");
+///
+/// // q)-8!{x}
+/// let lambda=encode(&QValue::Lambda{context: String::new(), body: "{x}".to_string()}, Endian::Little).unwrap();
+/// let error=decode(&lambda, Endian::Little, &Limits{max_depth: 0, ..Limits::default()}).unwrap_err();
+/// assert_eq!(error.message(), "depth: object at offset 0 is nested deeper than the limit of 0");
+/// // Deep nesting is rejected before it exhausts the stack.
+/// let mut nested=[0x00, 0x00, 0x01, 0x00, 0x00, 0x00].repeat(100_000);
+/// nested.extend_from_slice(&[0xf9, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+/// let error=decode(&nested, Endian::Little, &Limits::default()).unwrap_err();
+/// assert_eq!(error.message(), "depth: object at offset 384 is nested deeper than the limit of 64");
+/// // Chain of lambda headers
+/// let mut headers=[0x64, 0x00].repeat(100_000);
+/// headers.extend_from_slice(&[0x0a, 0x00, 0x01, 0x00, 0x00, 0x00, b'x']);
+/// let error=decode(&headers, Endian::Little, &Limits::default()).unwrap_err();
+/// assert_eq!(error.message(), "type: lambda at offset 0 must have a string at offset 2");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Limits{
+  /// Maximum depth of nested lists, dictionaries, tables and functions.
+  pub max_depth: usize,
+  /// Maximum number of elements of a list.
+  pub max_list_length: usize,
+  /// Maximum size of input in bytes.
+  pub max_size: usize
+}
+
+/// Cursor over bytes being decoded.
+pub(super) struct Decoder<'a>{
+  pub(super) bytes: &'a [u8],
+  pub(super) position: usize,
+  pub(super) endian: Endian,
+  pub(super) limits: &'a Limits,
+  pub(super) depth: usize,
+  /// Read lengths of lists in 64 bits for a large message.
+  pub(super) large: bool,
+  /// Set when the bytes end in the middle of a value, which more bytes may complete.
+  pub(super) truncated: bool,
+  /// End of a list as declared by its length, set when the list runs past the end of the bytes.
+  pub(super) declared_end: Option<usize>
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Implementation                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+impl Limits{
+  /// Default limits raised to accept large messages up to 1TB if the negotiated protocol version supports them.
+  pub fn for_version(version: u8) -> Self{
+    if supports_large_messages(version){
+      Limits{
+        max_list_length: i64::MAX as usize,
+        max_size: MAX_LARGE_MESSAGE_LENGTH as usize,
+        ..Limits::default()
+      }
+    }
+    else{
+      Limits::default()
+    }
+  }
+}
+
+impl Default for Limits{
+  /// 64 levels of nesting and up to 2GB, the limit of a message without the large message capability.
+  fn default() -> Self{
+    Limits{
+      max_depth: 64,
+      max_list_length: i32::MAX as usize,
+      max_size: i32::MAX as usize
+    }
+  }
+}
+
+impl<'a> Decoder<'a>{
+  /// Take the next `length` bytes.
+  pub(super) fn take(&mut self, length: usize) -> Result<&'a [u8], KError>{
+    if self.bytes.len() - self.position < length{
+      return Err(self.truncated(format!("expected {} bytes at offset {} but {} bytes remain", length, self.position, self.bytes.len() - self.position)));
+    }
+    let bytes=&self.bytes[self.position..self.position + length];
+    self.position+=length;
+    Ok(bytes)
+  }
+
+  pub(super) fn byte(&mut self) -> Result<u8, KError>{
+    Ok(self.take(1)?[0])
+  }
+
+  fn scalar<T: ReadScalar>(&mut self) -> Result<T, KError>{
+    let endian=self.endian;
+    Ok(T::read(self.take(T::SIZE)?, endian))
+  }
+
+  fn scalars<T: ReadScalar>(&mut self, length: usize) -> Result<Vec<T>, KError>{
+    let endian=self.endian;
+    Ok(self.take(length * T::SIZE)?.chunks_exact(T::SIZE).map(|bytes| T::read(bytes, endian)).collect())
+  }
+
+  /// Read a null-terminated string.
+  fn symbol(&mut self) -> Result<String, KError>{
+    let rest=&self.bytes[self.position..];
+    match rest.iter().position(|&byte| byte == 0){
+      Some(length) => {
+        let symbol=String::from_utf8_lossy(&rest[..length]).into_owned();
+        self.position+=length + 1;
+        Ok(symbol)
+      },
+      None => Err(self.truncated(format!("unterminated symbol at offset {}", self.position)))
+    }
+  }
+
+  /// Read a length of a list whose elements occupy at least `element_size` bytes. The length is checked
+  ///  against the limit and the remaining bytes before the list is allocated.
+  pub(super) fn length(&mut self, element_size: usize, offset: usize) -> Result<usize, KError>{
+    let length=if self.large {self.scalar::<i64>()?} else {self.scalar::<i32>()? as i64};
+    if length < 0{
+      return Err(KError::new(format!("length: negative length {} at offset {}", length, offset)));
+    }
+    let length=length as usize;
+    if length > self.limits.max_list_length{
+      return Err(KError::new(format!("length: list of {} elements at offset {} exceeds the limit of {}", length, offset, self.limits.max_list_length)));
+    }
+    let remaining=self.bytes.len() - self.position;
+    if length.saturating_mul(element_size) > remaining{
+      self.declared_end=Some(self.position.saturating_add(length.saturating_mul(element_size)));
+      return Err(self.truncated(format!("list of {} elements at offset {} needs at least {} bytes but {} bytes remain", length, offset, length.saturating_mul(element_size), remaining)));
+    }
+    Ok(length)
+  }
+
+  /// Error of bytes ending in the middle of a value.
+  pub(super) fn truncated(&mut self, reason: String) -> KError{
+    self.truncated=true;
+    KError::new(format!("truncated: {}", reason))
+  }
+
+  pub(super) fn attribute(&mut self) -> Result<Attribute, KError>{
+    let offset=self.position;
+    let byte=self.byte()?;
+    Attribute::from_byte(byte).ok_or_else(|| KError::new(format!("attribute: unknown attribute {} at offset {}", byte, offset)))
+  }
+
+  /// Enter a nested object.
+  pub(super) fn nest(&mut self, offset: usize) -> Result<(), KError>{
+    self.depth+=1;
+    if self.depth > self.limits.max_depth{
+      return Err(KError::new(format!("depth: object at offset {} is nested deeper than the limit of {}", offset, self.limits.max_depth)));
+    }
+    Ok(())
+  }
+
+  /// Check the type of the body of a lambda at `offset` before reading it so that a chain of lambda headers
+  ///  does not recurse. Missing bytes are left to be reported by reading the body.
+  pub(super) fn check_lambda_body(&self, offset: usize) -> Result<(), KError>{
+    match self.bytes.get(self.position){
+      Some(&body_type) if body_type as i8 != qtype::CHAR => Err(KError::new(format!("type: lambda at offset {} must have a string at offset {}", offset, self.position))),
+      _ => Ok(())
+    }
+  }
+
+  fn value(&mut self) -> Result<QValue, KError>{
+    let offset=self.position;
+    let qtype=self.byte()? as i8;
+    Ok(match qtype{
+      -1 => QValue::Bool(self.byte()? != 0),
+      -2 => QValue::Guid(self.take(16)?.try_into().unwrap()),
+      -4 => QValue::Byte(self.byte()?),
+      -5 => QValue::Short(self.scalar()?),
+      -6 => QValue::Int(self.scalar()?),
+      -7 => QValue::Long(self.scalar()?),
+      -8 => QValue::Real(self.scalar()?),
+      -9 => QValue::Float(self.scalar()?),
+      -10 => QValue::Char(self.byte()?),
+      -11 => QValue::Symbol(self.symbol()?),
+      -12 => QValue::Timestamp(self.scalar()?),
+      -13 => QValue::Month(self.scalar()?),
+      -14 => QValue::Date(self.scalar()?),
+      -15 => QValue::Datetime(self.scalar()?),
+      -16 => QValue::Timespan(self.scalar()?),
+      -17 => QValue::Minute(self.scalar()?),
+      -18 => QValue::Second(self.scalar()?),
+      -19 => QValue::Time(self.scalar()?),
+      0..=2 | 4..=19 => {
+        let attribute=self.attribute()?;
+        QValue::List(attribute, self.list(qtype, offset)?)
+      },
+      qtype::Table => {
+        let attribute=self.attribute()?;
+        self.nest(offset)?;
+        let dictionary=self.value()?;
+        self.depth-=1;
+        check_table(&dictionary, offset)?;
+        QValue::Table(attribute, Box::new(dictionary))
+      },
+      qtype::DICTIONARY | qtype::SORTED_DICTIONARY => {
+        self.nest(offset)?;
+        let keys=self.value()?;
+        let values=self.value()?;
+        self.depth-=1;
+        if let (Some(keys), Some(values))=(count(&keys), count(&values)){
+          if keys != values{
+            return Err(KError::new(format!("length: dictionary at offset {} has {} keys and {} values", offset, keys, values)));
+          }
+        }
+        QValue::Dictionary{sorted: qtype == qtype::SORTED_DICTIONARY, keys: Box::new(keys), values: Box::new(values)}
+      },
+      qtype::LAMBDA => {
+        let context=self.symbol()?;
+        let body_offset=self.position;
+        self.check_lambda_body(offset)?;
+        self.nest(offset)?;
+        let body=self.value()?;
+        self.depth-=1;
+        match body{
+          QValue::List(_, QList::Char(body)) => QValue::Lambda{context, body: String::from_utf8_lossy(&body).into_owned()},
+          _ => return Err(KError::new(format!("type: lambda at offset {} must have a string at offset {}", offset, body_offset)))
+        }
+      },
+      101 => QValue::Unary(self.byte()?),
+      102 => QValue::Operator(self.byte()?),
+      103 => QValue::Iterator(self.byte()?),
+      104 | 105 => {
+        let length=self.length(1, offset)?;
+        self.nest(offset)?;
+        let functions=(0..length).map(|_| self.value()).collect::<Result<Vec<_>, _>>()?;
+        self.depth-=1;
+        if qtype == 104 {QValue::Projection(functions)} else {QValue::Composition(functions)}
+      },
+      106..=111 => {
+        self.nest(offset)?;
+        let function=Box::new(self.value()?);
+        self.depth-=1;
+        match qtype{
+          106 => QValue::Each(function),
+          107 => QValue::Over(function),
+          108 => QValue::Scan(function),
+          109 => QValue::EachPrior(function),
+          110 => QValue::EachRight(function),
+          _ => QValue::EachLeft(function)
+        }
+      },
+      qtype::ERROR => QValue::Error(self.symbol()?),
+      -76..=-20 | 20..=76 => return Err(KError::new(format!("type: enumeration (type {}h) at offset {} is not representable", qtype, offset))),
+      _ => return Err(KError::new(format!("type: unknown type {}h at offset {}", qtype, offset)))
+    })
+  }
+
+  /// Read a length and elements of a list.
+  fn list(&mut self, qtype: i8, offset: usize) -> Result<QList, KError>{
+    let element_size=match qtype{
+      qtype::GUID => 16,
+      qtype::SHORT => 2,
+      qtype::INT | qtype::REAL | qtype::MONTH | qtype::DATE | qtype::MINUTE | qtype::SECOND | qtype::TIME => 4,
+      qtype::LONG | qtype::FLOAT | qtype::TIMESTAMP | qtype::DATETIME | qtype::TIMESPAN => 8,
+      // At least a type byte or a null character.
+      _ => 1
+    };
+    let length=self.length(element_size, offset)?;
+    Ok(match qtype{
+      qtype::COMPOUND => {
+        self.nest(offset)?;
+        let list=(0..length).map(|_| self.value()).collect::<Result<Vec<_>, _>>()?;
+        self.depth-=1;
+        QList::Mixed(list)
+      },
+      qtype::BOOL => QList::Bool(self.take(length)?.iter().map(|&bool| bool != 0).collect()),
+      qtype::GUID => QList::Guid(self.take(length * 16)?.chunks_exact(16).map(|guid| guid.try_into().unwrap()).collect()),
+      qtype::BYTE => QList::Byte(self.take(length)?.to_vec()),
+      qtype::SHORT => QList::Short(self.scalars(length)?),
+      qtype::INT => QList::Int(self.scalars(length)?),
+      qtype::LONG => QList::Long(self.scalars(length)?),
+      qtype::REAL => QList::Real(self.scalars(length)?),
+      qtype::FLOAT => QList::Float(self.scalars(length)?),
+      qtype::CHAR => QList::Char(self.take(length)?.to_vec()),
+      qtype::SYMBOL => QList::Symbol((0..length).map(|_| self.symbol()).collect::<Result<Vec<_>, _>>()?),
+      qtype::TIMESTAMP => QList::Timestamp(self.scalars(length)?),
+      qtype::MONTH => QList::Month(self.scalars(length)?),
+      qtype::DATE => QList::Date(self.scalars(length)?),
+      qtype::DATETIME => QList::Datetime(self.scalars(length)?),
+      qtype::TIMESPAN => QList::Timespan(self.scalars(length)?),
+      qtype::MINUTE => QList::Minute(self.scalars(length)?),
+      qtype::SECOND => QList::Second(self.scalars(length)?),
+      _ => QList::Time(self.scalars(length)?)
+    })
+  }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                             Deserialize                              //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Deserialize a value without the message header. The whole bytes must be consumed by the value.
+/// # Example
+/// ```
+/// use kdb_c_api::ipc::*;
+///
+/// let bytes=b"\x0b\x00\x02\x00\x00\x00a\x00b\x00";
+/// assert_eq!(decode(bytes, Endian::Little, &Limits::default()).unwrap(), QValue::List(Attribute::None, QList::Symbol(vec!["a".to_string(), "b".to_string()])));
+/// // Truncated
+/// let error=decode(&bytes[..8], Endian::Little, &Limits::default()).unwrap_err();
+/// assert_eq!(error.message(), "truncated: unterminated symbol at offset 8");
+/// ```
+pub fn decode(bytes: &[u8], endian: Endian, limits: &Limits) -> Result<QValue, KError>{
+  if bytes.len() > limits.max_size{
+    return Err(KError::new(format!("size: {} bytes exceed the limit of {}", bytes.len(), limits.max_size)));
+  }
+  decode_from(bytes, 0, endian, limits, false)
+}
+
+/// Deserialize an IPC message with a header and return the message type and the value. The length in the
+///  header must match the length of the message. A compressed message is decompressed first. A message
+///  exceeding 2GB is read in the large message format (see [`encode_message_with_version`](fn.encode_message_with_version.html))
+///  if `limits` allows it.
+/// # Example
+/// ```
+/// use kdb_c_api::ipc::*;
+///
+/// // q)-8!1
+/// let message=[0x01, 0x00, 0x00, 0x00, 0x11, 0x00, 0x00, 0x00, 0xf9, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+/// assert_eq!(decode_message(&message, &Limits::default()).unwrap(), (MessageType::Async, QValue::Long(1)));
+/// ```
+pub fn decode_message(message: &[u8], limits: &Limits) -> Result<(MessageType, QValue), KError>{
+  let (endian, message_type, compressed, length)=check_header(message, limits)?;
+  if compressed{
+    // Offsets in errors are counted in the decompressed message.
+    let decompressed=decompress(message, limits)?;
+    return Ok((message_type, decode_from(&decompressed, HEADER_LENGTH, endian, limits, false)?));
+  }
+  Ok((message_type, decode_from(message, HEADER_LENGTH, endian, limits, length > i32::MAX as u64)?))
+}
+
+/// Deserialize an IPC message into a q object like `d9`. An error sent in a response is returned as `KError`.
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+/// use kdb_c_api::ipc::*;
+///
+/// #[no_mangle]
+/// pub extern "C" fn rust_deserialize(bytes: K) -> K{
+///   match deserialize(bytes.as_mut_slice::<G>(), &Limits::default()){
+///     Ok(qobject) => qobject.into_raw(),
+///     Err(error) => error.to_q()
+///   }
+/// }
+/// ```
+/// ```q
+/// q)rust_deserialize: LIBPATH_ (`rust_deserialize; 1);
+/// q)rust_deserialize -8!`a`b!1 2
+/// a| 1
+/// b| 2
+/// q)rust_deserialize -1 _ -8!`a`b!1 2
+/// 'header: message length is 41 but 40 bytes are given
+/// ```
+/// # Note
+/// This function must be called on the main thread of q.
+pub fn deserialize(message: &[u8], limits: &Limits) -> Result<KOwned, KError>{
+  decode_message(message, limits)?.1.to_k()
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                              Utility                                 //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Check the header of a message against the whole message and return the byte order, the message type,
+///  the compressed flag and the message length.
+pub(super) fn check_header(message: &[u8], limits: &Limits) -> Result<(Endian, MessageType, bool, u64), KError>{
+  if message.len() > limits.max_size{
+    return Err(KError::new(format!("size: {} bytes exceed the limit of {}", message.len(), limits.max_size)));
+  }
+  let MessageHeader{endian, message_type, compressed, length}=MessageHeader::parse(message)?;
+  if length != message.len() as u64{
+    return Err(KError::new(format!("header: message length is {} but {} bytes are given", length, message.len())));
+  }
+  Ok((endian, message_type, compressed, length))
+}
+
+/// Decode a value starting from `start` which must end at the end of the bytes. Offsets in errors are
+///  counted from the head of the bytes.
+fn decode_from(bytes: &[u8], start: usize, endian: Endian, limits: &Limits, large: bool) -> Result<QValue, KError>{
+  let mut decoder=Decoder{bytes, position: start, endian, limits, depth: 0, large, truncated: false, declared_end: None};
+  let value=decoder.value()?;
+  if decoder.position != bytes.len(){
+    return Err(KError::new(format!("length: {} trailing bytes at offset {}", bytes.len() - decoder.position, decoder.position)));
+  }
+  Ok(value)
+}
+
+/// Get the number of items of a list, a dictionary or a table. `None` for other values.
+fn count(value: &QValue) -> Option<usize>{
+  match value{
+    QValue::List(_, list) => Some(list.len()),
+    QValue::Dictionary{keys, ..} => count(keys),
+    QValue::Table(_, dictionary) => match dictionary.as_ref(){
+      QValue::Dictionary{values, ..} => match values.as_ref(){
+        QValue::List(_, QList::Mixed(columns)) => columns.first().map_or(Some(0), count),
+        _ => None
+      },
+      _ => None
+    },
+    _ => None
+  }
+}
+
+/// Check that a table is a dictionary from column names to columns of the same length.
+fn check_table(dictionary: &QValue, offset: usize) -> Result<(), KError>{
+  let columns=match dictionary{
+    QValue::Dictionary{sorted: false, keys, values} => match (keys.as_ref(), values.as_ref()){
+      (QValue::List(_, QList::Symbol(_)), QValue::List(_, QList::Mixed(columns))) => columns,
+      _ => return Err(KError::new(format!("type: table at offset {} must map symbols to columns", offset)))
+    },
+    _ => return Err(KError::new(format!("type: table at offset {} must hold a dictionary", offset)))
+  };
+  let lengths=columns.iter().map(|column| match column{
+    QValue::List(_, list) => Some(list.len()),
+    _ => None
+  }).collect::<Option<Vec<_>>>();
+  match lengths{
+    Some(lengths) if lengths.windows(2).all(|pair| pair[0] == pair[1]) => Ok(()),
+    _ => Err(KError::new(format!("length: columns of table at offset {} must be lists of the same length", offset)))
+  }
+}
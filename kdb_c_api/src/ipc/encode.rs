@@ -0,0 +1,381 @@
+//! This module provides serialization of `QValue` into kdb+ IPC bytes, the same layout as `b9`.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Load Libraries                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use super::*;
+use crate::{K, KError};
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                                Traits                                //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Number written in the byte order of a message.
+pub(crate) trait Scalar: Copy{
+  /// Append bytes of the number to a buffer.
+  fn write(self, endian: Endian, buffer: &mut Vec<u8>);
+}
+
+macro_rules! impl_scalar {
+  ($($rust_type: ty),*) => {
+    $(
+      impl Scalar for $rust_type{
+        fn write(self, endian: Endian, buffer: &mut Vec<u8>){
+          match endian{
+            Endian::Little => buffer.extend_from_slice(&self.to_le_bytes()),
+            Endian::Big => buffer.extend_from_slice(&self.to_be_bytes())
+          }
+        }
+      }
+    )*
+  };
+}
+
+impl_scalar!(i16, i32, i64, f32, f64);
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                               Structs                                //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Buffer accumulating serialized bytes.
+struct Encoder{
+  buffer: Vec<u8>,
+  endian: Endian,
+  /// Write lengths of lists in 64 bits for a large message.
+  large: bool
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Implementation                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+impl Encoder{
+  fn byte(&mut self, byte: u8){
+    self.buffer.push(byte);
+  }
+
+  fn scalar<T: Scalar>(&mut self, number: T){
+    number.write(self.endian, &mut self.buffer);
+  }
+
+  fn scalars<T: Scalar>(&mut self, numbers: &[T]){
+    numbers.iter().for_each(|&number| self.scalar(number));
+  }
+
+  /// Write a null-terminated string. The string is truncated at a null character.
+  fn symbol(&mut self, symbol: &str){
+    self.buffer.extend(symbol.bytes().take_while(|&byte| byte != 0));
+    self.byte(0);
+  }
+
+  /// Write a length of a list. `'limit` is returned if it does not fit in `i32` unless the message is large.
+  fn length(&mut self, length: usize) -> Result<(), KError>{
+    if self.large{
+      self.scalar(length as i64);
+      return Ok(());
+    }
+    if length > i32::MAX as usize{
+      return Err(KError::new("limit"));
+    }
+    self.scalar(length as i32);
+    Ok(())
+  }
+
+  fn value(&mut self, value: &QValue) -> Result<(), KError>{
+    self.byte(value.qtype() as u8);
+    match value{
+      QValue::Bool(bool) => self.byte(*bool as u8),
+      QValue::Guid(guid) => self.buffer.extend_from_slice(guid),
+      QValue::Byte(byte) | QValue::Char(byte) => self.byte(*byte),
+      QValue::Short(short) => self.scalar(*short),
+      QValue::Int(int) | QValue::Month(int) | QValue::Date(int) | QValue::Minute(int) | QValue::Second(int) | QValue::Time(int) => self.scalar(*int),
+      QValue::Long(long) | QValue::Timestamp(long) | QValue::Timespan(long) => self.scalar(*long),
+      QValue::Real(real) => self.scalar(*real),
+      QValue::Float(float) | QValue::Datetime(float) => self.scalar(*float),
+      QValue::Symbol(symbol) | QValue::Error(symbol) => self.symbol(symbol),
+      QValue::List(attribute, list) => {
+        self.byte(*attribute as u8);
+        self.list(list)?;
+      },
+      QValue::Dictionary{keys, values, ..} => {
+        self.value(keys)?;
+        self.value(values)?;
+      },
+      QValue::Table(attribute, dictionary) => {
+        self.byte(*attribute as u8);
+        self.value(dictionary)?;
+      },
+      QValue::Lambda{context, body} => {
+        self.symbol(context);
+        self.value(&QValue::from(body.as_str()))?;
+      },
+      QValue::Unary(code) | QValue::Operator(code) | QValue::Iterator(code) => self.byte(*code),
+      QValue::Projection(functions) | QValue::Composition(functions) => {
+        self.length(functions.len())?;
+        for function in functions{
+          self.value(function)?;
+        }
+      },
+      QValue::Each(function) | QValue::Over(function) | QValue::Scan(function) | QValue::EachPrior(function) | QValue::EachRight(function) | QValue::EachLeft(function) => self.value(function)?
+    }
+    Ok(())
+  }
+
+  /// Write a length and elements of a list.
+  fn list(&mut self, list: &QList) -> Result<(), KError>{
+    self.length(list.len())?;
+    match list{
+      QList::Bool(list) => self.buffer.extend(list.iter().map(|&bool| bool as u8)),
+      QList::Guid(list) => list.iter().for_each(|guid| self.buffer.extend_from_slice(guid)),
+      QList::Byte(list) | QList::Char(list) => self.buffer.extend_from_slice(list),
+      QList::Short(list) => self.scalars(list),
+      QList::Int(list) | QList::Month(list) | QList::Date(list) | QList::Minute(list) | QList::Second(list) | QList::Time(list) => self.scalars(list),
+      QList::Long(list) | QList::Timestamp(list) | QList::Timespan(list) => self.scalars(list),
+      QList::Real(list) => self.scalars(list),
+      QList::Float(list) | QList::Datetime(list) => self.scalars(list),
+      QList::Symbol(list) => list.iter().for_each(|symbol| self.symbol(symbol)),
+      QList::Mixed(list) => {
+        for item in list{
+          self.value(item)?;
+        }
+      }
+    }
+    Ok(())
+  }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                              Serialize                               //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Serialize a value without the message header. `'limit` is returned if a list has more than `i32::MAX` elements.
+/// # Example
+/// ```
+/// use kdb_c_api::ipc::*;
+///
+/// // q)`a`b
+/// assert_eq!(encode(&QValue::List(Attribute::None, QList::Symbol(vec!["a".to_string(), "b".to_string()])), Endian::Little).unwrap(), b"\x0b\x00\x02\x00\x00\x00a\x00b\x00".to_vec());
+/// // Big endian
+/// assert_eq!(encode(&QValue::Int(1), Endian::Big).unwrap(), vec![0xfa, 0x00, 0x00, 0x00, 0x01]);
+/// ```
+pub fn encode(value: &QValue, endian: Endian) -> Result<Vec<u8>, KError>{
+  let mut encoder=Encoder{buffer: Vec::new(), endian, large: false};
+  encoder.value(value)?;
+  Ok(encoder.buffer)
+}
+
+/// Serialize a value into an IPC message with a header. The result is identical to `-8!` for an asynchronous
+///  message in little endian. `'limit` is returned if the message exceeds 2GB.
+/// # Example
+/// Outputs of `-8!` for each type:
+/// ```
+/// use kdb_c_api::ipc::*;
+///
+/// fn bytes(capture: &str) -> Vec<u8>{
+///   (2..capture.len()).step_by(2).map(|i| u8::from_str_radix(&capture[i..i+2], 16).unwrap()).collect()
+/// }
+/// fn symbols(attribute: Attribute, symbols: &[&str]) -> QValue{
+///   QValue::List(attribute, QList::Symbol(symbols.iter().map(|symbol| symbol.to_string()).collect()))
+/// }
+/// fn dictionary(sorted: bool, keys: QValue, values: QValue) -> QValue{
+///   QValue::Dictionary{sorted, keys: Box::new(keys), values: Box::new(values)}
+/// }
+/// fn table(column: &str, values: Vec<i64>) -> QValue{
+///   QValue::Table(Attribute::None, Box::new(dictionary(false, symbols(Attribute::None, &[column]), QValue::from(vec![QValue::from(values)]))))
+/// }
+///
+/// let captures=vec![
+///   // q)-8!1b
+///   (QValue::Bool(true), "0x010000000a000000ff01"),
+///   // q)-8!"G"$"0a369037-75d3-b24d-6721-5a1d44d4bed5"
+///   (QValue::Guid([0x0a, 0x36, 0x90, 0x37, 0x75, 0xd3, 0xb2, 0x4d, 0x67, 0x21, 0x5a, 0x1d, 0x44, 0xd4, 0xbe, 0xd5]), "0x0100000019000000fe0a36903775d3b24d67215a1d44d4bed5"),
+///   // q)-8!0x2a
+///   (QValue::Byte(0x2a), "0x010000000a000000fc2a"),
+///   // q)-8!1h
+///   (QValue::Short(1), "0x010000000b000000fb0100"),
+///   // q)-8!1i
+///   (QValue::Int(1), "0x010000000d000000fa01000000"),
+///   // q)-8!1
+///   (QValue::Long(1), "0x0100000011000000f90100000000000000"),
+///   // q)-8!1e
+///   (QValue::Real(1.0), "0x010000000d000000f80000803f"),
+///   // q)-8!1f
+///   (QValue::Float(1.0), "0x0100000011000000f7000000000000f03f"),
+///   // q)-8!"a"
+///   (QValue::Char(b'a'), "0x010000000a000000f661"),
+///   // q)-8!`abc
+///   (QValue::Symbol("abc".to_string()), "0x010000000d000000f561626300"),
+///   // q)-8!2000.01.01D00:00:00.000000001
+///   (QValue::Timestamp(1), "0x0100000011000000f40100000000000000"),
+///   // q)-8!2000.02m
+///   (QValue::Month(1), "0x010000000d000000f301000000"),
+///   // q)-8!2000.01.02
+///   (QValue::Date(1), "0x010000000d000000f201000000"),
+///   // q)-8!2000.01.02T00:00:00.000
+///   (QValue::Datetime(1.0), "0x0100000011000000f1000000000000f03f"),
+///   // q)-8!0D00:00:00.000000001
+///   (QValue::Timespan(1), "0x0100000011000000f00100000000000000"),
+///   // q)-8!00:01
+///   (QValue::Minute(1), "0x010000000d000000ef01000000"),
+///   // q)-8!00:00:01
+///   (QValue::Second(1), "0x010000000d000000ee01000000"),
+///   // q)-8!00:00:00.001
+///   (QValue::Time(1), "0x010000000d000000ed01000000"),
+///   // q)-8!01b
+///   (QValue::from(vec![false, true]), "0x01000000100000000100020000000001"),
+///   // q)-8!enlist 0x2a
+///   (QValue::from(vec![0x2a_u8]), "0x010000000f0000000400010000002a"),
+///   // q)-8!1 2h
+///   (QValue::from(vec![1_i16, 2]), "0x010000001200000005000200000001000200"),
+///   // q)-8!1 2i
+///   (QValue::from(vec![1_i32, 2]), "0x01000000160000000600020000000100000002000000"),
+///   // q)-8!`s#1 2
+///   (QValue::List(Attribute::Sorted, QList::Long(vec![1, 2])), "0x010000001e00000007010200000001000000000000000200000000000000"),
+///   // q)-8!1 2e
+///   (QValue::from(vec![1_f32, 2.0]), "0x01000000160000000800020000000000803f00000040"),
+///   // q)-8!1 2f
+///   (QValue::from(vec![1_f64, 2.0]), "0x010000001e000000090002000000000000000000f03f0000000000000040"),
+///   // q)-8!"ab"
+///   (QValue::from("ab"), "0x01000000100000000a00020000006162"),
+///   // q)-8!`u#`a`b
+///   (symbols(Attribute::Unique, &["a", "b"]), "0x01000000120000000b020200000061006200"),
+///   // q)-8!`p#1 1 2
+///   (QValue::List(Attribute::Parted, QList::Long(vec![1, 1, 2])), "0x0100000026000000070303000000010000000000000001000000000000000200000000000000"),
+///   // q)-8!`g#1 1 2
+///   (QValue::List(Attribute::Grouped, QList::Long(vec![1, 1, 2])), "0x0100000026000000070403000000010000000000000001000000000000000200000000000000"),
+///   // q)-8!2#0Ng
+///   (QValue::List(Attribute::None, QList::Guid(vec![[0; 16]; 2])), "0x010000002e0000000200020000000000000000000000000000000000000000000000000000000000000000000000"),
+///   // q)-8!enlist 2000.01.01D00:00:00.000000001
+///   (QValue::List(Attribute::None, QList::Timestamp(vec![1])), "0x01000000160000000c00010000000100000000000000"),
+///   // q)-8!enlist 2000.02m
+///   (QValue::List(Attribute::None, QList::Month(vec![1])), "0x01000000120000000d000100000001000000"),
+///   // q)-8!enlist 2000.01.02
+///   (QValue::List(Attribute::None, QList::Date(vec![1])), "0x01000000120000000e000100000001000000"),
+///   // q)-8!enlist 2000.01.02T00:00:00.000
+///   (QValue::List(Attribute::None, QList::Datetime(vec![1.0])), "0x01000000160000000f0001000000000000000000f03f"),
+///   // q)-8!enlist 0D00:00:00.000000001
+///   (QValue::List(Attribute::None, QList::Timespan(vec![1])), "0x01000000160000001000010000000100000000000000"),
+///   // q)-8!enlist 00:01
+///   (QValue::List(Attribute::None, QList::Minute(vec![1])), "0x010000001200000011000100000001000000"),
+///   // q)-8!enlist 00:00:01
+///   (QValue::List(Attribute::None, QList::Second(vec![1])), "0x010000001200000012000100000001000000"),
+///   // q)-8!enlist 00:00:00.001
+///   (QValue::List(Attribute::None, QList::Time(vec![1])), "0x010000001200000013000100000001000000"),
+///   // q)-8!(1; `a)
+///   (QValue::from(vec![QValue::Long(1), QValue::Symbol("a".to_string())]), "0x010000001a000000000002000000f90100000000000000f56100"),
+///   // q)-8!`a`b!1 2
+///   (dictionary(false, symbols(Attribute::None, &["a", "b"]), QValue::from(vec![1_i64, 2])), "0x0100000029000000630b00020000006100620007000200000001000000000000000200000000000000"),
+///   // q)-8!`s#`a`b!1 2
+///   (dictionary(true, symbols(Attribute::Sorted, &["a", "b"]), QValue::from(vec![1_i64, 2])), "0x01000000290000007f0b01020000006100620007000200000001000000000000000200000000000000"),
+///   // q)-8!([] a: 1 2)
+///   (table("a", vec![1, 2]), "0x010000002f0000006200630b0001000000610000000100000007000200000001000000000000000200000000000000"),
+///   // q)-8!([a: 1 2] b: 3 4)
+///   (dictionary(false, table("a", vec![1, 2]), table("b", vec![3, 4])), "0x0100000057000000636200630b00010000006100000001000000070002000000010000000000000002000000000000006200630b0001000000620000000100000007000200000003000000000000000400000000000000"),
+///   // q)-8!{x+y}
+///   (QValue::Lambda{context: String::new(), body: "{x+y}".to_string()}, "0x010000001500000064000a00050000007b782b797d"),
+///   // q)-8!(::)
+///   (QValue::Unary(0), "0x010000000a0000006500"),
+///   // q)-8!neg
+///   (QValue::Unary(2), "0x010000000a0000006502"),
+///   // q)-8!+
+///   (QValue::Operator(1), "0x010000000a0000006601"),
+///   // q)-8!+[1]
+///   (QValue::Projection(vec![QValue::Operator(1), QValue::Long(1)]), "0x010000001800000068020000006601f90100000000000000"),
+///   // q)-8!+'
+///   (QValue::Each(Box::new(QValue::Operator(1))), "0x010000000b0000006a6601"),
+///   // q)-8!+/
+///   (QValue::Over(Box::new(QValue::Operator(1))), "0x010000000b0000006b6601"),
+///   // q)-8!+\
+///   (QValue::Scan(Box::new(QValue::Operator(1))), "0x010000000b0000006c6601"),
+///   // q)-8!+':
+///   (QValue::EachPrior(Box::new(QValue::Operator(1))), "0x010000000b0000006d6601"),
+///   // q)-8!+/:
+///   (QValue::EachRight(Box::new(QValue::Operator(1))), "0x010000000b0000006e6601"),
+///   // q)-8!+\:
+///   (QValue::EachLeft(Box::new(QValue::Operator(1))), "0x010000000b0000006f6601"),
+/// ];
+/// for (value, capture) in captures{
+///   let message=bytes(capture);
+///   assert_eq!(encode_message(&value, MessageType::Async, Endian::Little).unwrap(), message);
+///   assert_eq!(decode_message(&message, &Limits::default()).unwrap(), (MessageType::Async, value));
+/// }
+/// ```
+pub fn encode_message(value: &QValue, message_type: MessageType, endian: Endian) -> Result<Vec<u8>, KError>{
+  encode_message_with_version(value, message_type, endian, PROTOCOL_VERSION)
+}
+
+/// Serialize a value into an IPC message for a connection of a negotiated protocol version. If the version
+///  supports large messages (see [`LARGE_MESSAGE_VERSION`](constant.LARGE_MESSAGE_VERSION.html)), a message
+///  exceeding 2GB is serialized in the large message format up to 1TB: the fourth byte of the header holds
+///  bits 32-39 of the message length and lengths of lists are written in 64 bits. Otherwise `'limit` is
+///  returned for such a message.
+/// # Example
+/// ```
+/// use kdb_c_api::ipc::*;
+///
+/// let version=negotiate_version(LARGE_MESSAGE_VERSION, PROTOCOL_VERSION);
+/// assert_eq!(version, PROTOCOL_VERSION);
+/// // Small message is identical in any version.
+/// let value=QValue::from(vec![1_i64, 2, 3]);
+/// assert_eq!(
+///   encode_message_with_version(&value, MessageType::Sync, Endian::Little, LARGE_MESSAGE_VERSION).unwrap(),
+///   encode_message(&value, MessageType::Sync, Endian::Little).unwrap()
+/// );
+/// ```
+pub fn encode_message_with_version(value: &QValue, message_type: MessageType, endian: Endian, version: u8) -> Result<Vec<u8>, KError>{
+  match frame(value, message_type, endian, false){
+    // Lengths do not fit in 32 bits.
+    Err(_) if supports_large_messages(version) => frame(value, message_type, endian, true),
+    result => result
+  }
+}
+
+/// Serialize a q object into an IPC message like `b9`, but in a given byte order and message type.
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+/// use kdb_c_api::ipc::*;
+///
+/// #[no_mangle]
+/// pub extern "C" fn serialize_big_endian(qobject: K) -> K{
+///   match unsafe{serialize(qobject, MessageType::Async, Endian::Big)}{
+///     Ok(message) => {
+///       let bytes=unsafe{ktn(qtype::BYTE as I, message.len() as J)};
+///       bytes.as_mut_slice::<G>().copy_from_slice(&message);
+///       bytes
+///     },
+///     Err(error) => error.to_q()
+///   }
+/// }
+/// ```
+/// ```q
+/// q)serialize_big_endian: LIBPATH_ (`serialize_big_endian; 1);
+/// q)serialize_big_endian 1i
+/// 0x000000000000000dfa00000001
+/// q)-9!serialize_big_endian 1i
+/// 1i
+/// ```
+/// # Safety
+/// `qobject` must be null or a valid q object.
+/// # Note
+/// This function must be called on the main thread of q.
+pub unsafe fn serialize(qobject: K, message_type: MessageType, endian: Endian) -> Result<Vec<u8>, KError>{
+  encode_message(&QValue::from_k(qobject)?, message_type, endian)
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                              Utility                                 //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Serialize a value with a header in the standard or the large message format.
+fn frame(value: &QValue, message_type: MessageType, endian: Endian, large: bool) -> Result<Vec<u8>, KError>{
+  let mut encoder=Encoder{buffer: vec![endian as u8, message_type as u8, 0, 0, 0, 0, 0, 0], endian, large};
+  encoder.value(value)?;
+  let length=encoder.buffer.len() as u64;
+  if length > if large {MAX_LARGE_MESSAGE_LENGTH} else {i32::MAX as u64}{
+    return Err(KError::new("limit"));
+  }
+  write_message_length(&mut encoder.buffer, length);
+  Ok(encoder.buffer)
+}
@@ -0,0 +1,352 @@
+//! This module provides framing of IPC messages over a stream. [`MessageReader`](struct.MessageReader.html)
+//!  accumulates bytes arriving in pieces from a non-blocking descriptor until a whole message is received,
+//!  and [`MessageWriter`](struct.MessageWriter.html) keeps the rest of a message which the descriptor could
+//!  not accept at once.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Load Libraries                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use super::*;
+use crate::{K, KError};
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                          Global Variables                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Bytes by which a buffer of a message grows at least.
+const MIN_GROWTH: usize=1 << 16;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                               Structs                                //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Header of IPC message.
+/// # Example
+/// ```
+/// use kdb_c_api::ipc::*;
+///
+/// // q)-8!1
+/// let header=MessageHeader::parse(&[0x01, 0x00, 0x00, 0x00, 0x11, 0x00, 0x00, 0x00]).unwrap();
+/// assert_eq!(header, MessageHeader{endian: Endian::Little, message_type: MessageType::Async, compressed: false, length: 17});
+/// assert_eq!(header.to_bytes(), [0x01, 0x00, 0x00, 0x00, 0x11, 0x00, 0x00, 0x00]);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MessageHeader{
+  /// Byte order of the message.
+  pub endian: Endian,
+  /// Type of the message.
+  pub message_type: MessageType,
+  /// Whether the message is compressed.
+  pub compressed: bool,
+  /// Length of the whole message including the header. Bits 32-39 are used by a large message.
+  pub length: u64
+}
+
+/// Buffer accumulating bytes of a message received in pieces.
+/// # Example
+/// ```
+/// use kdb_c_api::ipc::*;
+/// use std::io::Write;
+/// use std::os::unix::io::AsRawFd;
+/// use std::os::unix::net::UnixStream;
+///
+/// let (mut sender, receiver)=UnixStream::pair().unwrap();
+/// receiver.set_nonblocking(true).unwrap();
+/// let message=encode_message(&QValue::from(vec![1_i64, 2, 3]), MessageType::Async, Endian::Little).unwrap();
+/// let mut reader=MessageReader::new(Limits::default());
+/// // Nothing has arrived yet.
+/// assert_eq!(reader.read_fd(receiver.as_raw_fd()).unwrap(), None);
+/// sender.write_all(&message[..10]).unwrap();
+/// assert_eq!(reader.read_fd(receiver.as_raw_fd()).unwrap(), None);
+/// sender.write_all(&message[10..]).unwrap();
+/// assert_eq!(reader.read_fd(receiver.as_raw_fd()).unwrap(), Some(message));
+/// ```
+#[derive(Clone, Debug)]
+pub struct MessageReader{
+  buffer: Vec<u8>,
+  /// Number of bytes of the buffer filled so far.
+  received: usize,
+  /// Length of the message in the header, or `0` until the header arrives.
+  length: usize,
+  limits: Limits
+}
+
+/// Queue of messages sent through a descriptor which may accept only a part of a message at once.
+/// # Example
+/// ```
+/// use kdb_c_api::ipc::*;
+/// use std::os::unix::io::AsRawFd;
+/// use std::os::unix::net::UnixStream;
+///
+/// let (sender, receiver)=UnixStream::pair().unwrap();
+/// let mut writer=MessageWriter::new();
+/// writer.push_value(&QValue::Symbol("kdb".to_string()), MessageType::Sync).unwrap();
+/// assert!(writer.write_fd(sender.as_raw_fd()).unwrap());
+/// let mut reader=MessageReader::new(Limits::default());
+/// let message=reader.read_fd(receiver.as_raw_fd()).unwrap().unwrap();
+/// assert_eq!(decode_message(&message, &Limits::default()).unwrap(), (MessageType::Sync, QValue::Symbol("kdb".to_string())));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MessageWriter{
+  queue: VecDeque<Vec<u8>>,
+  /// Number of bytes of the first message already written.
+  written: usize
+}
+
+/// Borrowed file descriptor read and written with `libc`.
+#[cfg(unix)]
+struct Fd(RawFd);
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Implementation                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+impl MessageHeader{
+  /// Parse the first eight bytes of a message.
+  pub fn parse(header: &[u8]) -> Result<Self, KError>{
+    if header.len() < HEADER_LENGTH{
+      return Err(KError::new(format!("truncated: message of {} bytes is shorter than the header", header.len())));
+    }
+    let endian=match header[0]{
+      0 => Endian::Big,
+      1 => Endian::Little,
+      byte => return Err(KError::new(format!("header: unknown endianness {}", byte)))
+    };
+    let message_type=match header[1]{
+      0 => MessageType::Async,
+      1 => MessageType::Sync,
+      2 => MessageType::Response,
+      byte => return Err(KError::new(format!("header: unknown message type {}", byte)))
+    };
+    let compressed=match header[2]{
+      0 => false,
+      1 => true,
+      byte => return Err(KError::new(format!("header: unknown compression flag {}", byte)))
+    };
+    Ok(MessageHeader{endian, message_type, compressed, length: read_message_length(header, endian)})
+  }
+
+  /// Encode the header into eight bytes.
+  pub fn to_bytes(&self) -> [u8; HEADER_LENGTH]{
+    let mut header=[self.endian as u8, self.message_type as u8, self.compressed as u8, 0, 0, 0, 0, 0];
+    write_message_length(&mut header, self.length);
+    header
+  }
+}
+
+impl MessageReader{
+  /// Create a reader accepting messages within `max_size` of `limits`.
+  pub fn new(limits: Limits) -> Self{
+    MessageReader{buffer: vec![0; HEADER_LENGTH], received: 0, length: 0, limits}
+  }
+
+  /// Header of the message being received if it has arrived.
+  pub fn header(&self) -> Option<MessageHeader>{
+    if self.received < HEADER_LENGTH{
+      None
+    }
+    else{
+      MessageHeader::parse(&self.buffer).ok()
+    }
+  }
+
+  /// Read from a non-blocking reader until a whole message is received or no more bytes are available.
+  ///  The complete message including the header is returned and the reader is ready for the next message.
+  ///  `None` is returned if the message is not complete yet. Bytes beyond the message are not consumed.
+  ///
+  /// An error is returned for a malformed header, a message exceeding the limit or the stream closed by
+  ///  the peer. The stream cannot be read any more after an error. Memory is allocated as bytes arrive,
+  ///  not by the length in the header which a peer can forge.
+  /// # Example
+  /// ```
+  /// use kdb_c_api::ipc::*;
+  ///
+  /// // Header of a message of 1TB followed by nothing.
+  /// let mut stream: &[u8]=&[0x01, 0x01, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff];
+  /// let mut reader=MessageReader::new(Limits::for_version(LARGE_MESSAGE_VERSION));
+  /// assert_eq!(reader.read(&mut stream).unwrap_err().message(), "truncated: stream is closed after 8 bytes of a message");
+  /// ```
+  pub fn read<R: Read>(&mut self, reader: &mut R) -> Result<Option<Vec<u8>>, KError>{
+    loop{
+      if self.received == self.buffer.len(){
+        if self.length == 0{
+          // Header is complete.
+          let header=MessageHeader::parse(&self.buffer)?;
+          if header.length <= HEADER_LENGTH as u64{
+            return Err(KError::new(format!("header: invalid message length {}", header.length)));
+          }
+          if header.length > self.limits.max_size as u64{
+            return Err(KError::new(format!("size: {} bytes exceed the limit of {}", header.length, self.limits.max_size)));
+          }
+          self.length=header.length as usize;
+        }
+        if self.received == self.length{
+          self.received=0;
+          self.length=0;
+          return Ok(Some(std::mem::replace(&mut self.buffer, vec![0; HEADER_LENGTH])));
+        }
+        // Double the buffer up to the length of the message.
+        let length=self.length.min(self.buffer.len().max(MIN_GROWTH) * 2);
+        self.buffer.resize(length, 0);
+      }
+      match reader.read(&mut self.buffer[self.received..]){
+        Ok(0) if self.received == 0 => return Err(KError::new("closed: stream is closed by the peer")),
+        Ok(0) => return Err(KError::new(format!("truncated: stream is closed after {} bytes of a message", self.received))),
+        Ok(length) => self.received+=length,
+        Err(error) if error.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+        Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+        Err(error) => return Err(KError::new(format!("failed to read: {}", error)))
+      }
+    }
+  }
+
+  /// Read from a non-blocking file descriptor, e.g., a socket hooked by [`register_fd`](../fn.register_fd.html).
+  ///  See [`read`](struct.MessageReader.html#method.read).
+  #[cfg(unix)]
+  pub fn read_fd(&mut self, fd: RawFd) -> Result<Option<Vec<u8>>, KError>{
+    self.read(&mut Fd(fd))
+  }
+}
+
+impl MessageWriter{
+  /// Create an empty queue.
+  pub fn new() -> Self{
+    MessageWriter::default()
+  }
+
+  /// Check if all messages have been written.
+  pub fn is_empty(&self) -> bool{
+    self.queue.is_empty()
+  }
+
+  /// Append a framed message to the queue.
+  pub fn push(&mut self, message: Vec<u8>){
+    self.queue.push_back(message);
+  }
+
+  /// Frame a value in the byte order of the running machine and append it to the queue.
+  pub fn push_value(&mut self, value: &QValue, message_type: MessageType) -> Result<(), KError>{
+    self.push(encode_message(value, message_type, Endian::native())?);
+    Ok(())
+  }
+
+  /// Frame a q object in the byte order of the running machine and append it to the queue.
+  /// # Safety
+  /// `qobject` must be null or a valid q object.
+  /// # Note
+  /// This function must be called on the main thread of q.
+  pub unsafe fn push_k(&mut self, qobject: K, message_type: MessageType) -> Result<(), KError>{
+    self.push(serialize(qobject, message_type, Endian::native())?);
+    Ok(())
+  }
+
+  /// Write queued messages to a non-blocking writer as far as it accepts. Return `true` if the queue is
+  ///  emptied or `false` if the writer would block, in which case the rest is written on the next call.
+  pub fn write<W: Write>(&mut self, writer: &mut W) -> Result<bool, KError>{
+    while let Some(message)=self.queue.front(){
+      match writer.write(&message[self.written..]){
+        Ok(0) => return Err(KError::new("failed to write: stream accepts no more bytes")),
+        Ok(length) => {
+          self.written+=length;
+          if self.written == message.len(){
+            self.queue.pop_front();
+            self.written=0;
+          }
+        },
+        Err(error) if error.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+        Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+        Err(error) => return Err(KError::new(format!("failed to write: {}", error)))
+      }
+    }
+    Ok(true)
+  }
+
+  /// Write queued messages to a non-blocking file descriptor. See [`write`](struct.MessageWriter.html#method.write).
+  #[cfg(unix)]
+  pub fn write_fd(&mut self, fd: RawFd) -> Result<bool, KError>{
+    self.write(&mut Fd(fd))
+  }
+
+  /// Write all queued messages to a file descriptor, waiting for the descriptor to get writable if it is
+  ///  non-blocking.
+  #[cfg(unix)]
+  pub fn flush_fd(&mut self, fd: RawFd) -> Result<(), KError>{
+    while !self.write_fd(fd)?{
+      let mut poll_fd=libc::pollfd{fd, events: libc::POLLOUT, revents: 0};
+      if unsafe{libc::poll(&mut poll_fd, 1, -1)} == -1{
+        let error=io::Error::last_os_error();
+        if error.kind() != io::ErrorKind::Interrupted{
+          return Err(KError::new(format!("failed to poll: {}", error)));
+        }
+      }
+    }
+    Ok(())
+  }
+}
+
+#[cfg(unix)]
+impl Read for Fd{
+  fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize>{
+    match unsafe{libc::read(self.0, buffer.as_mut_ptr() as *mut libc::c_void, buffer.len())}{
+      -1 => Err(io::Error::last_os_error()),
+      length => Ok(length as usize)
+    }
+  }
+}
+
+#[cfg(unix)]
+impl Write for Fd{
+  fn write(&mut self, buffer: &[u8]) -> io::Result<usize>{
+    match unsafe{libc::write(self.0, buffer.as_ptr() as *const libc::c_void, buffer.len())}{
+      -1 => Err(io::Error::last_os_error()),
+      length => Ok(length as usize)
+    }
+  }
+
+  fn flush(&mut self) -> io::Result<()>{
+    Ok(())
+  }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                                Send                                  //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Frame a value and write the whole message to a file descriptor, e.g., a socket of a q process, waiting
+///  for the descriptor to get writable if it is non-blocking.
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+/// use kdb_c_api::ipc::*;
+///
+/// extern "C" fn counter(socket: I) -> K{
+///   let query=QValue::from("show `$\"Counter_punch!!\"");
+///   if let Err(error)=send_value(socket, &query, MessageType::Async){
+///     eprintln!("counter: {}", error);
+///   }
+///   KNULL!()
+/// }
+/// ```
+#[cfg(unix)]
+pub fn send_value(fd: RawFd, value: &QValue, message_type: MessageType) -> Result<(), KError>{
+  let mut writer=MessageWriter::new();
+  writer.push_value(value, message_type)?;
+  writer.flush_fd(fd)
+}
+
+/// Frame a q object and write the whole message to a file descriptor. See [`send_value`](fn.send_value.html).
+/// # Safety
+/// `qobject` must be null or a valid q object.
+/// # Note
+/// This function must be called on the main thread of q.
+#[cfg(unix)]
+pub unsafe fn send_k(fd: RawFd, qobject: K, message_type: MessageType) -> Result<(), KError>{
+  let mut writer=MessageWriter::new();
+  writer.push_k(qobject, message_type)?;
+  writer.flush_fd(fd)
+}
@@ -0,0 +1,562 @@
+//! This module provides access to a journal (log file) of a tickerplant replayed by `-11!`. A journal is a
+//!  general list saved by `.[file; (); :; ()]` and appended through a handle to the file: it starts with
+//!  `0xff01` followed by the type, the attribute and the count of the list, and each item, called a chunk,
+//!  is a serialized message like `` (`upd; `trade; data) `` without the IPC header.
+//!
+//! [`JournalReader`](struct.JournalReader.html) replays a journal and [`JournalWriter`](struct.JournalWriter.html)
+//!  writes a journal which q can replay by `-11!`.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Load Libraries                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use super::*;
+use super::decode::{Decoder, ReadScalar};
+use super::encode::Scalar;
+use crate::{qtype, KError};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                          Global Variables                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Length of the header of a journal.
+pub const JOURNAL_HEADER_LENGTH: usize=8;
+
+/// Bytes read from a journal at least at once.
+const READ_LENGTH: usize=1 << 16;
+
+/// Offset of the count of chunks in the header.
+const COUNT_OFFSET: u64=4;
+
+/// Days from 1970.01.01 to 2000.01.01.
+const UNIX_EPOCH_DATE: i64=10957;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                               Structs                                //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Reader of a journal yielding chunks one by one. Only the chunk being read is kept in memory. Iteration
+///  stops at a truncated last chunk, e.g., left by a tickerplant killed while writing, which can be told by
+///  [`is_truncated`](struct.JournalReader.html#method.is_truncated). A corrupt chunk is yielded as an error
+///  and ends the iteration.
+/// # Example
+/// ```
+/// use kdb_c_api::ipc::*;
+///
+/// // q)`:trade.log set ()
+/// // q)h: hopen `:trade.log
+/// // q)h enlist (`upd; `trade; (`kdb`rust; 1 2))
+/// let chunk=QValue::from(vec![
+///   QValue::Symbol("upd".to_string()),
+///   QValue::Symbol("trade".to_string()),
+///   QValue::from(vec![QValue::List(Attribute::None, QList::Symbol(vec!["kdb".to_string(), "rust".to_string()])), QValue::from(vec![1_i64, 2])])
+/// ]);
+/// let mut journal=vec![0xff, 0x01, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00];
+/// journal.extend(encode(&chunk, Endian::native()).unwrap());
+/// journal.extend(encode(&chunk, Endian::native()).unwrap());
+/// // Truncated chunk
+/// journal.extend_from_slice(&[0x00, 0x00, 0x03, 0x00]);
+///
+/// let mut reader=JournalReader::new(journal.as_slice()).unwrap();
+/// assert_eq!(reader.recorded_chunks(), 2);
+/// assert_eq!(reader.next().unwrap().unwrap(), chunk);
+/// assert_eq!(reader.next().unwrap().unwrap(), chunk);
+/// assert!(reader.next().is_none());
+/// assert!(reader.is_truncated());
+/// assert_eq!(reader.valid_length(), journal.len() as u64 - 4);
+///
+/// // q)-11!(-2; `:trade.log)
+/// let count=JournalReader::new(journal.as_slice()).unwrap().count_chunks().unwrap();
+/// assert_eq!(count, JournalCount{chunks: 2, valid_length: journal.len() as u64 - 4, length: journal.len() as u64});
+/// assert!(!count.is_valid());
+/// ```
+#[derive(Debug)]
+pub struct JournalReader<R: Read>{
+  reader: R,
+  /// Bytes read but not consumed yet.
+  buffer: Vec<u8>,
+  /// Position of the next chunk in the buffer.
+  position: usize,
+  /// Offset of the head of the buffer in the journal.
+  offset: u64,
+  /// Count of the list written in the header.
+  recorded_chunks: usize,
+  /// Number of complete chunks read so far.
+  chunks: usize,
+  limits: Limits,
+  /// Length of the journal if it is known from the file, to tell a corrupt length of a list from a chunk
+  ///  not read yet.
+  length: Option<u64>,
+  end_of_file: bool,
+  truncated: bool,
+  /// Iteration is over due to an error.
+  failed: bool
+}
+
+/// Number of valid chunks of a journal like `-11!(-2; file)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct JournalCount{
+  /// Number of valid chunks from the head of the journal.
+  pub chunks: usize,
+  /// Length of the header and the valid chunks in bytes.
+  pub valid_length: u64,
+  /// Length of the journal in bytes.
+  pub length: u64
+}
+
+/// Timing of `fsync` of a journal written by [`JournalWriter`](struct.JournalWriter.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyncPolicy{
+  /// Leave flushing to the OS as a tickerplant does.
+  Never,
+  /// Sync after every chunk.
+  Always,
+  /// Sync after every given number of chunks.
+  Every(usize),
+  /// Sync on writing a chunk if the given time has passed since the last sync.
+  Interval(Duration)
+}
+
+/// Writer appending chunks to a journal which q can replay by `-11!`. The count of chunks in the header is
+///  updated after each chunk as q does for a handle to the file.
+/// # Example
+/// ```
+/// use kdb_c_api::ipc::*;
+///
+/// let directory=std::env::temp_dir().join(format!("kdb_c_api_journal_{}", std::process::id()));
+/// std::fs::create_dir_all(&directory).unwrap();
+///
+/// // Journal named after the date like a tickerplant.
+/// let mut writer=JournalWriter::daily(&directory, "sym", SyncPolicy::Every(100)).unwrap();
+/// // 2024.01.02 given by the caller from now on.
+/// writer.roll(8767).unwrap();
+/// assert_eq!(writer.path(), directory.join("sym2024.01.02"));
+/// // q)upd[`trade; (`kdb`rust; 1 2)]
+/// let data=QValue::from(vec![QValue::List(Attribute::None, QList::Symbol(vec!["kdb".to_string(), "rust".to_string()])), QValue::from(vec![1_i64, 2])]);
+/// writer.upd("trade", data.clone()).unwrap();
+/// writer.upd("trade", data.clone()).unwrap();
+/// writer.sync().unwrap();
+/// assert_eq!(writer.path(), directory.join("sym2024.01.02"));
+///
+/// // q)get `:sym2024.01.02
+/// let mut reader=JournalReader::open(directory.join("sym2024.01.02")).unwrap();
+/// assert_eq!(reader.recorded_chunks(), 2);
+/// assert_eq!(reader.next().unwrap().unwrap(), QValue::from(vec![QValue::Symbol("upd".to_string()), QValue::Symbol("trade".to_string()), data]));
+/// assert_eq!(count_journal(directory.join("sym2024.01.02")).unwrap().chunks, 2);
+/// std::fs::remove_dir_all(&directory).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct JournalWriter{
+  file: File,
+  path: PathBuf,
+  /// Number of chunks in the journal.
+  chunks: usize,
+  policy: SyncPolicy,
+  /// Number of chunks written since the last sync.
+  unsynced: usize,
+  last_sync: Instant,
+  daily: Option<Daily>
+}
+
+/// Journal switched to a new file every day.
+#[derive(Clone, Debug)]
+struct Daily{
+  directory: PathBuf,
+  prefix: String,
+  /// Date of the current file as days from 2000.01.01.
+  date: i32,
+  /// Roll to the current date on writing a chunk. Cleared once the date is given by the caller.
+  wall_clock: bool
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Implementation                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+//%% JournalReader %%//vvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvv/
+
+impl JournalReader<BufReader<File>>{
+  /// Open a journal file. A list whose length runs past the end of the file is reported as a corrupt chunk
+  ///  without reading the rest of the file.
+  /// # Example
+  /// ```
+  /// use kdb_c_api::ipc::*;
+  ///
+  /// let path=std::env::temp_dir().join(format!("kdb_c_api_corrupt_{}", std::process::id()));
+  /// let chunk=encode(&QValue::from(vec![1_i64, 2]), Endian::native()).unwrap();
+  /// let mut journal=vec![0xff, 0x01, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00];
+  /// journal.extend(&chunk);
+  /// // List of 0x7fffffff longs
+  /// journal.extend_from_slice(&[0x07, 0x00, 0xff, 0xff, 0xff, 0x7f]);
+  /// journal.extend(&chunk);
+  /// std::fs::write(&path, &journal).unwrap();
+  ///
+  /// let mut reader=JournalReader::open(&path).unwrap();
+  /// assert!(reader.next().unwrap().is_ok());
+  /// assert!(reader.next().unwrap().unwrap_err().message().starts_with("journal: chunk 1 at offset 30 is corrupt."));
+  /// assert!(!reader.is_truncated());
+  /// assert_eq!(count_journal(&path).unwrap(), JournalCount{chunks: 1, valid_length: 30, length: journal.len() as u64});
+  /// std::fs::remove_file(&path).unwrap();
+  /// ```
+  pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, KError>{
+    let file=File::open(path.as_ref()).map_err(|error| os_error(path.as_ref(), error))?;
+    let length=file.metadata().map_err(|error| os_error(path.as_ref(), error))?.len();
+    let mut journal=JournalReader::new(BufReader::new(file))?;
+    journal.length=Some(length);
+    Ok(journal)
+  }
+}
+
+impl<R: Read> JournalReader<R>{
+  /// Read and check the header of a journal. The size of a chunk is not limited since a journal is written
+  ///  by a trusted tickerplant; only nesting is limited by the default of [`Limits`](struct.Limits.html).
+  pub fn new(reader: R) -> Result<Self, KError>{
+    JournalReader::with_limits(reader, Limits{max_list_length: usize::MAX, max_size: usize::MAX, ..Limits::default()})
+  }
+
+  /// Read and check the header of a journal whose chunks are decoded within limits. A chunk exceeding
+  ///  the limits is yielded as an error.
+  /// # Example
+  /// ```
+  /// use kdb_c_api::ipc::*;
+  ///
+  /// let mut journal=vec![0xff, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00];
+  /// journal.extend(encode(&QValue::from(vec![1_i64, 2, 3]), Endian::native()).unwrap());
+  /// let limits=Limits{max_list_length: 2, ..Limits::default()};
+  /// let mut reader=JournalReader::with_limits(journal.as_slice(), limits).unwrap();
+  /// assert!(reader.next().unwrap().unwrap_err().message().starts_with("journal: chunk 0 at offset 8 is corrupt. length:"));
+  ///
+  /// // Chain of lambda headers
+  /// let mut journal=vec![0xff, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00];
+  /// journal.extend([0x64, 0x00].repeat(100_000));
+  /// journal.extend_from_slice(&[0x0a, 0x00, 0x01, 0x00, 0x00, 0x00, b'x']);
+  /// let mut reader=JournalReader::with_limits(journal.as_slice(), Limits::default()).unwrap();
+  /// assert!(reader.next().unwrap().unwrap_err().message().ends_with("type: lambda at offset 0 must have a string at offset 2 of the chunk"));
+  /// ```
+  pub fn with_limits(reader: R, limits: Limits) -> Result<Self, KError>{
+    let mut journal=JournalReader{
+      reader,
+      buffer: Vec::new(),
+      position: 0,
+      offset: 0,
+      recorded_chunks: 0,
+      chunks: 0,
+      limits,
+      length: None,
+      end_of_file: false,
+      truncated: false,
+      failed: false
+    };
+    while journal.buffer.len() < JOURNAL_HEADER_LENGTH && journal.fill()?{}
+    let header=&journal.buffer;
+    if header.len() < JOURNAL_HEADER_LENGTH || header[..2] != [0xff, 0x01] || header[2] as i8 != qtype::COMPOUND{
+      return Err(KError::new("journal: not a journal file"));
+    }
+    journal.recorded_chunks=i32::read(&header[4..JOURNAL_HEADER_LENGTH], Endian::native()).max(0) as usize;
+    journal.position=JOURNAL_HEADER_LENGTH;
+    Ok(journal)
+  }
+
+  /// Count of the list written in the header. It can differ from the number of chunks actually written
+  ///  if the writer was stopped abnormally.
+  pub fn recorded_chunks(&self) -> usize{
+    self.recorded_chunks
+  }
+
+  /// Number of complete chunks read so far.
+  pub fn chunks(&self) -> usize{
+    self.chunks
+  }
+
+  /// Length of the header and the complete chunks read so far in bytes.
+  pub fn valid_length(&self) -> u64{
+    self.offset + self.position as u64
+  }
+
+  /// Check if the iteration stopped at a truncated last chunk.
+  pub fn is_truncated(&self) -> bool{
+    self.truncated
+  }
+
+  /// Count the remaining valid chunks without decoding them and consume the reader.
+  pub fn count_chunks(mut self) -> Result<JournalCount, KError>{
+    // A corrupt chunk ends the valid part as a truncated chunk does.
+    while let Ok(Some(_))=self.next_chunk(){}
+    let rest=io::copy(&mut self.reader, &mut io::sink()).map_err(|error| KError::new(format!("journal: failed to read: {}", error)))?;
+    Ok(JournalCount{chunks: self.chunks, valid_length: self.valid_length(), length: self.offset + self.buffer.len() as u64 + rest})
+  }
+
+  /// Find the range of the next complete chunk in the buffer reading more bytes as needed. `None` is
+  ///  returned at the end of the journal or at a truncated chunk.
+  fn next_chunk(&mut self) -> Result<Option<Range<usize>>, KError>{
+    loop{
+      if self.position < self.buffer.len(){
+        // Offsets in errors are counted from the head of the chunk.
+        let mut decoder=Decoder{bytes: &self.buffer[self.position..], position: 0, endian: Endian::native(), limits: &self.limits, depth: 0, large: false, truncated: false, declared_end: None};
+        match decoder.skip_value(){
+          Ok(_) => {
+            let chunk=self.position..self.position + decoder.position;
+            self.position=chunk.end;
+            self.chunks+=1;
+            return Ok(Some(chunk));
+          },
+          Err(_) if decoder.truncated => {
+            // Length of a list beyond the end of the file is corrupt rather than waiting for more bytes.
+            if let (Some(end), Some(length))=(decoder.declared_end, self.length){
+              let end=self.valid_length().saturating_add(end as u64);
+              if end > length{
+                return Err(KError::new(format!("journal: chunk {} at offset {} is corrupt. length: list ends at offset {} beyond the end of the journal at {}", self.chunks, self.valid_length(), end, length)));
+              }
+            }
+            if self.end_of_file{
+              self.truncated=true;
+              return Ok(None);
+            }
+          },
+          Err(error) => return Err(KError::new(format!("journal: chunk {} at offset {} is corrupt. {} of the chunk", self.chunks, self.valid_length(), error)))
+        }
+      }
+      else if self.end_of_file{
+        return Ok(None);
+      }
+      self.fill()?;
+    }
+  }
+
+  /// Drop consumed bytes and read more bytes, at least as many as already buffered so that a large chunk
+  ///  is not scanned over and over. Return `false` at the end of the file.
+  fn fill(&mut self) -> Result<bool, KError>{
+    self.offset+=self.position as u64;
+    self.buffer.drain(..self.position);
+    self.position=0;
+    let length=self.buffer.len().max(READ_LENGTH);
+    match self.reader.by_ref().take(length as u64).read_to_end(&mut self.buffer){
+      Ok(0) => {
+        self.end_of_file=true;
+        Ok(false)
+      },
+      Ok(_) => Ok(true),
+      Err(error) => Err(KError::new(format!("journal: failed to read: {}", error)))
+    }
+  }
+}
+
+impl<R: Read> Iterator for JournalReader<R>{
+  type Item=Result<QValue, KError>;
+  fn next(&mut self) -> Option<Self::Item>{
+    if self.failed{
+      return None;
+    }
+    let result=self.next_chunk().and_then(|chunk| match chunk{
+      Some(chunk) => decode(&self.buffer[chunk], Endian::native(), &self.limits).map(Some),
+      None => Ok(None)
+    });
+    if result.is_err(){
+      self.failed=true;
+    }
+    result.transpose()
+  }
+}
+
+//%% JournalWriter %%//vvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvv/
+
+impl JournalWriter{
+  /// Open a journal to append chunks like `.u.ld` of a tickerplant. A new journal is created if the file does
+  ///  not exist. An existing journal must consist of valid chunks.
+  pub fn open<P: AsRef<Path>>(path: P, policy: SyncPolicy) -> Result<Self, KError>{
+    let path=path.as_ref().to_path_buf();
+    let (file, chunks)=open_journal(&path)?;
+    Ok(JournalWriter{file, path, chunks, policy, unsynced: 0, last_sync: Instant::now(), daily: None})
+  }
+
+  /// Open a journal in a directory named after the current date in UTC like `sym2024.01.02` for a prefix
+  ///  `sym`. The journal is switched to a new file when a chunk is written on a new date until the date is
+  ///  given by [`roll`](struct.JournalWriter.html#method.roll).
+  pub fn daily<P: AsRef<Path>>(directory: P, prefix: &str, policy: SyncPolicy) -> Result<Self, KError>{
+    let date=today();
+    let daily=Daily{directory: directory.as_ref().to_path_buf(), prefix: prefix.to_string(), date, wall_clock: true};
+    let mut writer=JournalWriter::open(daily.path(), policy)?;
+    writer.daily=Some(daily);
+    Ok(writer)
+  }
+
+  /// Path of the current journal.
+  pub fn path(&self) -> &Path{
+    &self.path
+  }
+
+  /// Number of chunks in the current journal.
+  pub fn chunks(&self) -> usize{
+    self.chunks
+  }
+
+  /// Append a chunk.
+  pub fn append(&mut self, chunk: &QValue) -> Result<(), KError>{
+    if let Some(daily)=&self.daily{
+      let date=today();
+      if daily.wall_clock && date != daily.date{
+        self.switch(date)?;
+      }
+    }
+    if self.chunks >= i32::MAX as usize{
+      return Err(KError::new("limit"));
+    }
+    let bytes=encode(chunk, Endian::native())?;
+    let mut count=Vec::with_capacity(4);
+    (self.chunks as i32 + 1).write(Endian::native(), &mut count);
+    let end=self.file.seek(SeekFrom::End(0)).map_err(|error| os_error(&self.path, error))?;
+    // The count is updated after the chunk is written so that it does not exceed the valid chunks.
+    let written=self.file.write_all(&bytes)
+      .and_then(|_| self.file.seek(SeekFrom::Start(COUNT_OFFSET)))
+      .and_then(|_| self.file.write_all(&count));
+    if let Err(error)=written{
+      // Drop a partial chunk, e.g., left by a full disk, so that the next chunk follows the valid ones.
+      let mut previous=Vec::with_capacity(4);
+      (self.chunks as i32).write(Endian::native(), &mut previous);
+      let _=self.file.set_len(end)
+        .and_then(|_| self.file.seek(SeekFrom::Start(COUNT_OFFSET)))
+        .and_then(|_| self.file.write_all(&previous));
+      return Err(os_error(&self.path, error));
+    }
+    self.chunks+=1;
+    self.unsynced+=1;
+    let due=match self.policy{
+      SyncPolicy::Never => false,
+      SyncPolicy::Always => true,
+      SyncPolicy::Every(chunks) => self.unsynced >= chunks,
+      SyncPolicy::Interval(interval) => self.last_sync.elapsed() >= interval
+    };
+    if due{
+      self.sync()?;
+    }
+    Ok(())
+  }
+
+  /// Append an update `` (`upd; `table; data) `` of a tickerplant.
+  pub fn upd(&mut self, table: &str, data: QValue) -> Result<(), KError>{
+    self.append(&QValue::from(vec![QValue::Symbol("upd".to_string()), QValue::Symbol(table.to_string()), data]))
+  }
+
+  /// Flush written chunks to the disk by `fsync`.
+  pub fn sync(&mut self) -> Result<(), KError>{
+    self.file.sync_data().map_err(|error| os_error(&self.path, error))?;
+    self.unsynced=0;
+    self.last_sync=Instant::now();
+    Ok(())
+  }
+
+  /// Sync the current journal and switch to the journal of a date given as days from 2000.01.01 like
+  ///  `.u.end` followed by `.u.ld`. Only a journal opened by [`daily`](struct.JournalWriter.html#method.daily)
+  ///  can be rolled. The journal is no longer rolled by the clock after this; the caller rolls it on the
+  ///  next date as a tickerplant does by its timer.
+  pub fn roll(&mut self, date: i32) -> Result<(), KError>{
+    self.switch(date)?;
+    if let Some(daily)=&mut self.daily{
+      daily.wall_clock=false;
+    }
+    Ok(())
+  }
+
+  /// Sync the current journal and switch to the journal of a date.
+  fn switch(&mut self, date: i32) -> Result<(), KError>{
+    let mut daily=match &self.daily{
+      Some(daily) => daily.clone(),
+      None => return Err(KError::new("journal: not a daily journal"))
+    };
+    self.sync()?;
+    daily.date=date;
+    let path=daily.path();
+    let (file, chunks)=open_journal(&path)?;
+    *self=JournalWriter{file, path, chunks, policy: self.policy, unsynced: 0, last_sync: Instant::now(), daily: Some(daily)};
+    Ok(())
+  }
+}
+
+impl Daily{
+  /// Path of the journal of the date.
+  fn path(&self) -> PathBuf{
+    self.directory.join(format!("{}{}", self.prefix, format_date(self.date)))
+  }
+}
+
+//%% JournalCount %%//vvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvv/
+
+impl JournalCount{
+  /// Check if the whole journal consists of valid chunks, in which case `-11!(-2; file)` returns only
+  ///  the number of chunks.
+  pub fn is_valid(&self) -> bool{
+    self.valid_length == self.length
+  }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                                Count                                 //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Count valid chunks of a journal file like `-11!(-2; file)`. Chunks are scanned without being decoded.
+/// # Example
+/// ```no_run
+/// use kdb_c_api::ipc::*;
+///
+/// let count=count_journal("/data/tp/sym2024.01.02").unwrap();
+/// if !count.is_valid(){
+///   eprintln!("{} valid chunks in {} of {} bytes", count.chunks, count.valid_length, count.length);
+/// }
+/// ```
+pub fn count_journal<P: AsRef<Path>>(path: P) -> Result<JournalCount, KError>{
+  JournalReader::open(path)?.count_chunks()
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                              Utility                                 //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Open a journal for writing, creating it with an empty header if it does not exist, and return the file
+///  and the number of chunks.
+fn open_journal(path: &Path) -> Result<(File, usize), KError>{
+  let mut file=OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path).map_err(|error| os_error(path, error))?;
+  let length=file.metadata().map_err(|error| os_error(path, error))?.len();
+  if length == 0{
+    // q).[file; (); :; ()]
+    file.write_all(&[0xff, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]).map_err(|error| os_error(path, error))?;
+    return Ok((file, 0));
+  }
+  let mut reader=JournalReader::new(BufReader::new(&file))?;
+  reader.length=Some(length);
+  let count=reader.count_chunks()?;
+  if !count.is_valid(){
+    return Err(KError::new(format!("journal: {} has {} valid chunks in {} of {} bytes", path.display(), count.chunks, count.valid_length, count.length)));
+  }
+  Ok((file, count.chunks))
+}
+
+/// Error of a file operation in the same form as q.
+fn os_error(path: &Path, error: io::Error) -> KError{
+  KError::new(format!("{}. OS reports: {}", path.display(), error))
+}
+
+/// Current date in UTC as days from 2000.01.01.
+fn today() -> i32{
+  let seconds=SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs() as i64);
+  (seconds / 86400 - UNIX_EPOCH_DATE) as i32
+}
+
+/// Format days from 2000.01.01 like `2024.01.02`.
+fn format_date(date: i32) -> String{
+  // Civil date from days (http://howardhinnant.github.io/date_algorithms.html).
+  let days=date as i64 + UNIX_EPOCH_DATE + 719468;
+  let era=days.div_euclid(146097);
+  let day_of_era=days - era * 146097;
+  let year_of_era=(day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+  let day_of_year=day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let month_from_march=(5 * day_of_year + 2) / 153;
+  let day=day_of_year - (153 * month_from_march + 2) / 5 + 1;
+  let month=if month_from_march < 10 {month_from_march + 3} else {month_from_march - 9};
+  let year=year_of_era + era * 400 + (month <= 2) as i64;
+  format!("{:04}.{:02}.{:02}", year, month, day)
+}
@@ -0,0 +1,144 @@
+//! This module provides a pure-Rust implementation of kdb+ IPC protocol. q objects are represented by
+//!  [`QValue`](enum.QValue.html), which can be built without q, and are serialized into the same bytes as
+//!  `b9` (`-8!`) produces. Hence messages can be built and parsed both inside a shared library loaded by q
+//!  and in a standalone Rust process.
+//!
+//! Inside q, a `K` object is converted into `QValue` by [`QValue::from_k`](enum.QValue.html#method.from_k)
+//!  and back by [`QValue::to_k`](enum.QValue.html#method.to_k).
+//!
+//! # Example
+//! ```
+//! use kdb_c_api::ipc::*;
+//!
+//! // q)-8!1
+//! let message=encode_message(&QValue::Long(1), MessageType::Async, Endian::Little).unwrap();
+//! assert_eq!(message, vec![0x01, 0x00, 0x00, 0x00, 0x11, 0x00, 0x00, 0x00, 0xf9, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+//! assert_eq!(decode_message(&message, &Limits::default()).unwrap(), (MessageType::Async, QValue::Long(1)));
+//! ```
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Load Libraries                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+mod value;
+mod encode;
+mod decode;
+mod compress;
+mod validate;
+mod frame;
+mod journal;
+#[cfg(feature="client")]
+mod client;
+#[cfg(feature="server")]
+mod server;
+
+pub use value::{QValue, QList, Attribute};
+pub use encode::{encode, encode_message, encode_message_with_version, serialize};
+pub use decode::{decode, decode_message, deserialize, Limits};
+pub use compress::{compress, decompress, COMPRESSION_THRESHOLD};
+pub use validate::{validate_message, is_valid_message};
+pub use frame::{MessageHeader, MessageReader, MessageWriter};
+pub use journal::{JournalReader, JournalWriter, JournalCount, SyncPolicy, count_journal, JOURNAL_HEADER_LENGTH};
+#[cfg(unix)]
+pub use frame::{send_value, send_k};
+#[cfg(feature="client")]
+pub use client::{Connection, ConnectError};
+#[cfg(feature="server")]
+pub use server::{Server, Session};
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                          Global Variables                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Length of the header of IPC message.
+pub const HEADER_LENGTH: usize=8;
+
+/// Protocol version supporting compression, timestamp, timespan and GUID (kdb+ 3.0+). Sent as the
+///  capability byte in the handshake.
+pub const PROTOCOL_VERSION: u8=3;
+
+/// Protocol version additionally supporting messages over 2GB up to 1TB (kdb+ 4.0+). Requested by the
+///  capability `1` of [`khpunc`](../fn.khpunc.html).
+pub const LARGE_MESSAGE_VERSION: u8=6;
+
+/// Maximum length of a large message (1TB).
+pub const MAX_LARGE_MESSAGE_LENGTH: u64=1 << 40;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                               Structs                                //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Byte order of IPC message. Indicated by the first byte of the header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endian{
+  /// Big endian. Indicated by `0`.
+  Big=0,
+  /// Little endian. Indicated by `1`.
+  Little=1
+}
+
+/// Type of IPC message. Indicated by the second byte of the header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessageType{
+  /// Asynchronous message (`neg[h] x`).
+  Async=0,
+  /// Synchronous message (`h x`).
+  Sync=1,
+  /// Response to a synchronous message.
+  Response=2
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Implementation                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+impl Endian{
+  /// Byte order of the running machine, which `b9` uses.
+  pub fn native() -> Self{
+    if cfg!(target_endian="big") {Endian::Big} else {Endian::Little}
+  }
+}
+
+impl Default for Endian{
+  fn default() -> Self{
+    Endian::native()
+  }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                             Negotiation                              //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Get the protocol version used by a connection from the capability byte requested by a client and the
+///  highest version supported by a server.
+pub fn negotiate_version(requested: u8, supported: u8) -> u8{
+  requested.min(supported)
+}
+
+/// Check if a protocol version supports messages over 2GB.
+pub fn supports_large_messages(version: u8) -> bool{
+  version >= LARGE_MESSAGE_VERSION
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                              Utility                                 //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Read the message length from a header. The fourth byte holds bits 32-39 of a large message.
+pub(crate) fn read_message_length(header: &[u8], endian: Endian) -> u64{
+  let low=match endian{
+    Endian::Little => u32::from_le_bytes([header[4], header[5], header[6], header[7]]),
+    Endian::Big => u32::from_be_bytes([header[4], header[5], header[6], header[7]])
+  };
+  (header[3] as u64) << 32 | low as u64
+}
+
+/// Write the message length into a header.
+pub(crate) fn write_message_length(header: &mut [u8], length: u64){
+  let low=match header[0]{
+    0 => (length as u32).to_be_bytes(),
+    _ => (length as u32).to_le_bytes()
+  };
+  header[3]=(length >> 32) as u8;
+  header[4..HEADER_LENGTH].copy_from_slice(&low);
+}
@@ -0,0 +1,251 @@
+//! This module provides a server speaking kdb+ IPC protocol so that a Rust process can be opened by `hopen`
+//!  and queried like a q process. Each connection is served on its own thread. Enabled by the feature `server`.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Load Libraries                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use super::*;
+use crate::KError;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                          Global Variables                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Closure checking a user and a password like `.z.pw`.
+type Authenticate=dyn Fn(&str, &str) -> bool + Send + Sync;
+
+/// Maximum length of a handshake. A longer handshake is rejected.
+const MAX_HANDSHAKE_LENGTH: usize=1024;
+
+/// Time allowed for a client to send a handshake. A connection sending nothing is closed after this.
+const HANDSHAKE_TIMEOUT: Duration=Duration::from_secs(10);
+
+/// Default maximum size of a message received by a server.
+const DEFAULT_MAX_MESSAGE_SIZE: usize=1 << 30;
+
+/// Pause after a failure of accepting a connection, e.g., by running out of file descriptors.
+const ACCEPT_RETRY_INTERVAL: Duration=Duration::from_millis(100);
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                               Structs                                //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Server accepting connections from q processes.
+/// # Example
+/// ```
+/// use kdb_c_api::KError;
+/// use kdb_c_api::ipc::*;
+/// use std::io::{Read, Write};
+/// use std::net::TcpStream;
+///
+/// let server=Server::bind_with_authentication("127.0.0.1:0", |user, password| user == "kdb" && password == "rust").unwrap();
+/// let port=server.local_addr().unwrap().port();
+/// std::thread::spawn(move || {
+///   // Echo a query except for `oops`.
+///   server.serve(|session: &Session, _, query| match query{
+///     QValue::Symbol(symbol) if symbol == "oops" => Err(KError::new(format!("oops from {}", session.user))),
+///     query => Ok(query)
+///   })
+/// });
+///
+/// // q)h: hopen `:127.0.0.1:port:kdb:rust
+/// let mut stream=TcpStream::connect(("127.0.0.1", port)).unwrap();
+/// stream.write_all(b"kdb:rust\x03\x00").unwrap();
+/// let mut version=[0_u8];
+/// stream.read_exact(&mut version).unwrap();
+/// assert_eq!(version[0], PROTOCOL_VERSION);
+/// let mut reader=MessageReader::new(Limits::default());
+/// // q)h `oops
+/// stream.write_all(&encode_message(&QValue::Symbol("oops".to_string()), MessageType::Sync, Endian::Little).unwrap()).unwrap();
+/// let response=reader.read(&mut stream).unwrap().unwrap();
+/// assert_eq!(decode_message(&response, &Limits::default()).unwrap(), (MessageType::Response, QValue::Error("oops from kdb".to_string())));
+///
+/// // q)hopen `:127.0.0.1:port:kdb:q
+/// let mut stream=TcpStream::connect(("127.0.0.1", port)).unwrap();
+/// stream.write_all(b"kdb:q\x03\x00").unwrap();
+/// // Closed without a reply.
+/// assert_eq!(stream.read(&mut version).unwrap(), 0);
+/// ```
+pub struct Server{
+  listener: TcpListener,
+  authenticate: Arc<Authenticate>,
+  /// Maximum size of a message received from a client.
+  max_size: usize
+}
+
+/// Client of a connection passed to a handler of [`Server`](struct.Server.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Session{
+  /// User sent in the handshake like `.z.u`.
+  pub user: String,
+  /// Address of the client like `.z.a`.
+  pub address: SocketAddr,
+  /// Protocol version negotiated in the handshake.
+  pub version: u8
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Implementation                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+impl Server{
+  /// Listen on an address accepting any user.
+  pub fn bind<A: ToSocketAddrs>(address: A) -> Result<Self, KError>{
+    Server::bind_with_authentication(address, |_, _| true)
+  }
+
+  /// Listen on an address accepting a user for whom `authenticate` returns `true` with the password. The
+  ///  connection is closed for other users, which `hopen` reports as `'access`.
+  pub fn bind_with_authentication<A, F>(address: A, authenticate: F) -> Result<Self, KError> where A: ToSocketAddrs, F: Fn(&str, &str) -> bool + Send + Sync + 'static{
+    match TcpListener::bind(address){
+      Ok(listener) => Ok(Server{listener, authenticate: Arc::new(authenticate), max_size: DEFAULT_MAX_MESSAGE_SIZE}),
+      Err(error) => Err(KError::new(format!("failed to bind: {}", error)))
+    }
+  }
+
+  /// Address the server is listening on.
+  pub fn local_addr(&self) -> Result<SocketAddr, KError>{
+    self.listener.local_addr().map_err(|error| KError::new(error.to_string()))
+  }
+
+  /// Set the maximum size of a message received from a client, 1GB by default. A connection sending a
+  ///  larger message is closed. The limit applies even if the client negotiates large messages.
+  /// # Example
+  /// ```
+  /// use kdb_c_api::ipc::*;
+  /// use std::io::{Read, Write};
+  /// use std::net::TcpStream;
+  ///
+  /// let mut server=Server::bind("127.0.0.1:0").unwrap();
+  /// server.set_max_size(1 << 20);
+  /// let port=server.local_addr().unwrap().port();
+  /// std::thread::spawn(move || server.serve(|_, _, query| Ok(query)));
+  ///
+  /// // Header of a message of 1TB from a client capable of large messages.
+  /// let mut stream=TcpStream::connect(("127.0.0.1", port)).unwrap();
+  /// stream.write_all(b"\x06\x00").unwrap();
+  /// let mut version=[0_u8];
+  /// stream.read_exact(&mut version).unwrap();
+  /// assert_eq!(version[0], LARGE_MESSAGE_VERSION);
+  /// stream.write_all(&[0x01, 0x01, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff]).unwrap();
+  /// // Closed without allocating the message.
+  /// assert_eq!(stream.read(&mut version).unwrap(), 0);
+  ///
+  /// // Server is still serving.
+  /// let mut stream=TcpStream::connect(("127.0.0.1", port)).unwrap();
+  /// stream.write_all(b"\x03\x00").unwrap();
+  /// stream.read_exact(&mut version).unwrap();
+  /// assert_eq!(version[0], PROTOCOL_VERSION);
+  /// ```
+  pub fn set_max_size(&mut self, max_size: usize){
+    self.max_size=max_size;
+  }
+
+  /// Accept connections forever, serving each connection on a new thread. `handler` receives the client,
+  ///  the type of a message and the decoded query. For a synchronous message, the returned value is sent
+  ///  back as the response and an error is sent back as a q error like `'oops`. The return value is discarded
+  ///  for an asynchronous message.
+  ///
+  /// A connection is closed when the client closes it or sends bytes which cannot be framed as a message,
+  ///  or when the handshake is not completed within 10 seconds. A failure of accepting a connection, e.g.,
+  ///  by running out of file descriptors, is reported to stderr and the server keeps accepting.
+  pub fn serve<H>(&self, handler: H) -> Result<(), KError> where H: Fn(&Session, MessageType, QValue) -> Result<QValue, KError> + Send + Sync + 'static{
+    let handler=Arc::new(handler);
+    for stream in self.listener.incoming(){
+      let stream=match stream{
+        Ok(stream) => stream,
+        Err(error) => {
+          eprintln!("kdb_c_api: failed to accept a connection: {}", error);
+          std::thread::sleep(ACCEPT_RETRY_INTERVAL);
+          continue;
+        }
+      };
+      let authenticate=Arc::clone(&self.authenticate);
+      let handler=Arc::clone(&handler);
+      let max_size=self.max_size;
+      std::thread::spawn(move || {
+        if let Some(session)=accept(&stream, authenticate.as_ref()){
+          serve_connection(stream, session, max_size, handler.as_ref());
+        }
+      });
+    }
+    Ok(())
+  }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                              Utility                                 //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Read a handshake of `user:password`, an optional capability byte and a null character, and reply the
+///  negotiated protocol version if the user is authenticated.
+fn accept(mut stream: &TcpStream, authenticate: &Authenticate) -> Option<Session>{
+  let address=stream.peer_addr().ok()?;
+  stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT)).ok()?;
+  let mut handshake=Vec::new();
+  let mut byte=[0_u8];
+  loop{
+    stream.read_exact(&mut byte).ok()?;
+    if byte[0] == 0{
+      break;
+    }
+    handshake.push(byte[0]);
+    if handshake.len() > MAX_HANDSHAKE_LENGTH{
+      return None;
+    }
+  }
+  // A client older than kdb+ 2.6 sends no capability.
+  let capability=match handshake.last(){
+    Some(&capability) if capability < b' ' => {
+      handshake.pop();
+      capability
+    },
+    _ => 0
+  };
+  let credential=String::from_utf8_lossy(&handshake);
+  let (user, password)=credential.split_once(':').unwrap_or((&credential, ""));
+  if !authenticate(user, password){
+    return None;
+  }
+  let version=negotiate_version(capability, LARGE_MESSAGE_VERSION);
+  stream.write_all(&[version]).ok()?;
+  stream.set_read_timeout(None).ok()?;
+  Some(Session{user: user.to_string(), address, version})
+}
+
+/// Decode messages within `max_size` from a connection and dispatch them to a handler until the connection
+///  is closed.
+fn serve_connection<H>(mut stream: TcpStream, session: Session, max_size: usize, handler: &H) where H: Fn(&Session, MessageType, QValue) -> Result<QValue, KError>{
+  let mut limits=Limits::for_version(session.version);
+  limits.max_size=limits.max_size.min(max_size);
+  let compress_response=!session.address.ip().is_loopback();
+  let mut reader=MessageReader::new(limits.clone());
+  while let Ok(Some(message))=reader.read(&mut stream){
+    let message_type=match MessageHeader::parse(&message){
+      Ok(header) => header.message_type,
+      Err(_) => return
+    };
+    let result=decode_message(&message, &limits).and_then(|(_, query)| handler(&session, message_type, query));
+    if message_type != MessageType::Sync{
+      continue;
+    }
+    let response=result.unwrap_or_else(|error| QValue::Error(error.message().to_string()));
+    let mut response=match encode_message_with_version(&response, MessageType::Response, Endian::native(), session.version){
+      Ok(response) => response,
+      Err(error) => encode_message(&QValue::Error(error.message().to_string()), MessageType::Response, Endian::native()).unwrap()
+    };
+    if compress_response && response.len() > COMPRESSION_THRESHOLD{
+      if let Some(compressed)=compress(&response){
+        response=compressed;
+      }
+    }
+    if stream.write_all(&response).is_err(){
+      return;
+    }
+  }
+}
@@ -0,0 +1,279 @@
+//! This module provides validation of kdb+ IPC messages in the same manner as `okx`, but without q. Bytes
+//!  are walked through without building any value, so that a socket handler registered by `sd1` can
+//!  reject a malformed message before passing it to `d9`.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Load Libraries                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use super::*;
+use super::decode::{check_header, Decoder};
+use crate::{qtype, KError};
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Implementation                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+impl<'a> Decoder<'a>{
+  /// Skip a null-terminated string.
+  fn skip_symbol(&mut self) -> Result<(), KError>{
+    match self.bytes[self.position..].iter().position(|&byte| byte == 0){
+      Some(length) => {
+        self.position+=length + 1;
+        Ok(())
+      },
+      None => Err(self.truncated(format!("unterminated symbol at offset {}", self.position)))
+    }
+  }
+
+  /// Skip a value and return its type and the number of items if it is a list, a dictionary or a table.
+  ///  The same errors as decoding the value are returned.
+  pub(super) fn skip_value(&mut self) -> Result<(i8, Option<usize>), KError>{
+    let offset=self.position;
+    let qtype=self.byte()? as i8;
+    let count=match qtype{
+      -1 | -4 | -10 => {
+        self.take(1)?;
+        None
+      },
+      -2 => {
+        self.take(16)?;
+        None
+      },
+      -5 => {
+        self.take(2)?;
+        None
+      },
+      -6 | -8 | -13 | -14 | -17 | -18 | -19 => {
+        self.take(4)?;
+        None
+      },
+      -7 | -9 | -12 | -15 | -16 => {
+        self.take(8)?;
+        None
+      },
+      -11 | qtype::ERROR => {
+        self.skip_symbol()?;
+        None
+      },
+      0..=2 | 4..=19 => {
+        self.attribute()?;
+        Some(self.skip_list(qtype, offset)?)
+      },
+      qtype::Table => {
+        self.attribute()?;
+        self.nest(offset)?;
+        let count=self.skip_table(offset)?;
+        self.depth-=1;
+        Some(count)
+      },
+      qtype::DICTIONARY | qtype::SORTED_DICTIONARY => {
+        self.nest(offset)?;
+        let (_, keys)=self.skip_value()?;
+        let (_, values)=self.skip_value()?;
+        self.depth-=1;
+        if let (Some(keys), Some(values))=(keys, values){
+          if keys != values{
+            return Err(KError::new(format!("length: dictionary at offset {} has {} keys and {} values", offset, keys, values)));
+          }
+        }
+        keys
+      },
+      qtype::LAMBDA => {
+        self.skip_symbol()?;
+        let body_offset=self.position;
+        self.check_lambda_body(offset)?;
+        self.nest(offset)?;
+        let (body_type, _)=self.skip_value()?;
+        self.depth-=1;
+        if body_type != qtype::CHAR{
+          return Err(KError::new(format!("type: lambda at offset {} must have a string at offset {}", offset, body_offset)));
+        }
+        None
+      },
+      101..=103 => {
+        self.take(1)?;
+        None
+      },
+      104 | 105 => {
+        let length=self.length(1, offset)?;
+        self.nest(offset)?;
+        for _ in 0..length{
+          self.skip_value()?;
+        }
+        self.depth-=1;
+        None
+      },
+      106..=111 => {
+        self.nest(offset)?;
+        self.skip_value()?;
+        self.depth-=1;
+        None
+      },
+      -76..=-20 | 20..=76 => return Err(KError::new(format!("type: enumeration (type {}h) at offset {} is not representable", qtype, offset))),
+      _ => return Err(KError::new(format!("type: unknown type {}h at offset {}", qtype, offset)))
+    };
+    Ok((qtype, count))
+  }
+
+  /// Skip a length and elements of a list and return the length.
+  fn skip_list(&mut self, qtype: i8, offset: usize) -> Result<usize, KError>{
+    let element_size=match qtype{
+      qtype::GUID => 16,
+      qtype::SHORT => 2,
+      qtype::INT | qtype::REAL | qtype::MONTH | qtype::DATE | qtype::MINUTE | qtype::SECOND | qtype::TIME => 4,
+      qtype::LONG | qtype::FLOAT | qtype::TIMESTAMP | qtype::DATETIME | qtype::TIMESPAN => 8,
+      _ => 1
+    };
+    let length=self.length(element_size, offset)?;
+    match qtype{
+      qtype::COMPOUND => {
+        self.nest(offset)?;
+        for _ in 0..length{
+          self.skip_value()?;
+        }
+        self.depth-=1;
+      },
+      qtype::SYMBOL => {
+        for _ in 0..length{
+          self.skip_symbol()?;
+        }
+      },
+      // Length is already checked against the remaining bytes.
+      _ => self.position+=length * element_size
+    }
+    Ok(length)
+  }
+
+  /// Skip a dictionary of a table checking that it maps symbols to columns of the same length, and return
+  ///  the number of rows. Errors are checked in the same order as decoding the table.
+  fn skip_table(&mut self, offset: usize) -> Result<usize, KError>{
+    let dictionary_offset=self.position;
+    if self.bytes.get(dictionary_offset).copied() != Some(qtype::DICTIONARY as u8){
+      self.skip_value()?;
+      return Err(KError::new(format!("type: table at offset {} must hold a dictionary", offset)));
+    }
+    self.position+=1;
+    self.nest(dictionary_offset)?;
+    let (keys_type, names)=self.skip_value()?;
+    let values_offset=self.position;
+    if self.bytes.get(values_offset).copied() != Some(qtype::COMPOUND as u8){
+      let (_, values)=self.skip_value()?;
+      if let (Some(names), Some(values))=(names, values){
+        if names != values{
+          return Err(KError::new(format!("length: dictionary at offset {} has {} keys and {} values", dictionary_offset, names, values)));
+        }
+      }
+      return Err(KError::new(format!("type: table at offset {} must map symbols to columns", offset)));
+    }
+    self.position+=1;
+    self.attribute()?;
+    let length=self.length(1, values_offset)?;
+    self.nest(values_offset)?;
+    // Rows of columns if they are lists of the same length.
+    let mut rows=Some(None);
+    for _ in 0..length{
+      rows=match (rows, self.skip_value()?){
+        (Some(None), (0..=19, Some(count))) => Some(Some(count)),
+        (Some(Some(rows)), (0..=19, Some(count))) if rows == count => Some(Some(rows)),
+        _ => None
+      };
+    }
+    self.depth-=2;
+    if let Some(names)=names{
+      if names != length{
+        return Err(KError::new(format!("length: dictionary at offset {} has {} keys and {} values", dictionary_offset, names, length)));
+      }
+    }
+    if keys_type != qtype::SYMBOL{
+      return Err(KError::new(format!("type: table at offset {} must map symbols to columns", offset)));
+    }
+    match rows{
+      Some(rows) => Ok(rows.unwrap_or(0)),
+      None => Err(KError::new(format!("length: columns of table at offset {} must be lists of the same length", offset)))
+    }
+  }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                              Validate                                //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Validate bytes as a complete IPC message: the header, the length in the header against the length of
+///  the bytes, the compressed flag and the types and lengths of all nested objects. The same error as
+///  [`decode_message`](fn.decode_message.html) is returned for an invalid message, but no value is built.
+///  A compressed message is decompressed into a buffer first.
+/// # Example
+/// ```
+/// use kdb_c_api::ipc::*;
+///
+/// // q)-8!`a`b!1 2
+/// let message=encode_message(&QValue::Dictionary{
+///   sorted: false,
+///   keys: Box::new(QValue::List(Attribute::None, QList::Symbol(vec!["a".to_string(), "b".to_string()]))),
+///   values: Box::new(QValue::from(vec![1_i64, 2]))
+/// }, MessageType::Async, Endian::Little).unwrap();
+/// assert!(validate_message(&message, &Limits::default()).is_ok());
+/// // Truncated
+/// let error=validate_message(&message[..message.len() - 1], &Limits::default()).unwrap_err();
+/// assert_eq!(error.message(), "header: message length is 41 but 40 bytes are given");
+/// // Unknown type
+/// let mut garbage=message.clone();
+/// garbage[8]=3;
+/// assert_eq!(validate_message(&garbage, &Limits::default()).unwrap_err().message(), "type: unknown type 3h at offset 8");
+/// // Chain of lambda headers
+/// let mut headers=[0x64, 0x00].repeat(100_000);
+/// headers.extend_from_slice(&[0x0a, 0x00, 0x01, 0x00, 0x00, 0x00, b'x']);
+/// let mut message=vec![0x01, 0x00, 0x00, 0x00];
+/// message.extend_from_slice(&(headers.len() as u32 + 8).to_le_bytes());
+/// message.extend(headers);
+/// assert_eq!(validate_message(&message, &Limits::default()).unwrap_err().message(), "type: lambda at offset 8 must have a string at offset 10");
+/// // q)-8!{x}
+/// let lambda=encode_message(&QValue::Lambda{context: String::new(), body: "{x}".to_string()}, MessageType::Async, Endian::Little).unwrap();
+/// let error=validate_message(&lambda, &Limits{max_depth: 0, ..Limits::default()}).unwrap_err();
+/// assert_eq!(error.message(), "depth: object at offset 8 is nested deeper than the limit of 0");
+/// ```
+pub fn validate_message(message: &[u8], limits: &Limits) -> Result<(), KError>{
+  let (endian, _, compressed, length)=check_header(message, limits)?;
+  if compressed{
+    let decompressed=decompress(message, limits)?;
+    return validate_from(&decompressed, endian, limits, false);
+  }
+  validate_from(message, endian, limits, length > i32::MAX as u64)
+}
+
+/// Check if bytes are a complete and well-formed IPC message with the default limits like `okx`.
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+/// use kdb_c_api::ipc::*;
+///
+/// #[no_mangle]
+/// pub extern "C" fn rust_okx(bytes: K) -> K{
+///   unsafe{kb(is_valid_message(bytes.as_mut_slice::<G>()) as I)}
+/// }
+/// ```
+/// ```q
+/// q)rust_okx: LIBPATH_ (`rust_okx; 1);
+/// q)rust_okx -8!1 2 3
+/// 1b
+/// q)rust_okx -1 _ -8!1 2 3
+/// 0b
+/// ```
+pub fn is_valid_message(message: &[u8]) -> bool{
+  validate_message(message, &Limits::default()).is_ok()
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                              Utility                                 //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Skip a value following the header which must end at the end of the message.
+fn validate_from(message: &[u8], endian: Endian, limits: &Limits, large: bool) -> Result<(), KError>{
+  let mut decoder=Decoder{bytes: message, position: HEADER_LENGTH, endian, limits, depth: 0, large, truncated: false, declared_end: None};
+  decoder.skip_value()?;
+  if decoder.position != message.len(){
+    return Err(KError::new(format!("length: {} trailing bytes at offset {}", message.len() - decoder.position, decoder.position)));
+  }
+  Ok(())
+}
@@ -0,0 +1,549 @@
+//! This module provides `QValue`, a q object held in Rust memory, and its conversion from `K`.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Load Libraries                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use crate::*;
+use super::*;
+use std::ffi::{CStr, CString};
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                               Structs                                //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Attribute of q list.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Attribute{
+  /// No attribute.
+  #[default]
+  None=0,
+  /// Sorted (`` `s# ``).
+  Sorted=1,
+  /// Unique (`` `u# ``).
+  Unique=2,
+  /// Parted (`` `p# ``).
+  Parted=3,
+  /// Grouped (`` `g# ``).
+  Grouped=4
+}
+
+/// q object held in Rust memory. Unlike `K`, this value can be created, sent to another thread and
+///  serialized without q. Temporal values hold the same underlying numbers as q, e.g., nanoseconds since
+///  `2000.01.01D00:00:00` for timestamp.
+#[derive(Clone, Debug, PartialEq)]
+pub enum QValue{
+  /// Bool atom.
+  Bool(bool),
+  /// GUID atom.
+  Guid([u8; 16]),
+  /// Byte atom.
+  Byte(u8),
+  /// Short atom.
+  Short(i16),
+  /// Int atom.
+  Int(i32),
+  /// Long atom.
+  Long(i64),
+  /// Real atom.
+  Real(f32),
+  /// Float atom.
+  Float(f64),
+  /// Char atom.
+  Char(u8),
+  /// Symbol atom.
+  Symbol(String),
+  /// Timestamp atom (nanoseconds since 2000.01.01).
+  Timestamp(i64),
+  /// Month atom (months since 2000.01).
+  Month(i32),
+  /// Date atom (days since 2000.01.01).
+  Date(i32),
+  /// Datetime atom (days since 2000.01.01).
+  Datetime(f64),
+  /// Timespan atom (nanoseconds).
+  Timespan(i64),
+  /// Minute atom (minutes).
+  Minute(i32),
+  /// Second atom (seconds).
+  Second(i32),
+  /// Time atom (milliseconds).
+  Time(i32),
+  /// List.
+  List(Attribute, QList),
+  /// Dictionary. `sorted` is `true` for a sorted dictionary (type `127h`).
+  Dictionary{
+    sorted: bool,
+    keys: Box<QValue>,
+    values: Box<QValue>
+  },
+  /// Table holding a dictionary from column names to columns.
+  Table(Attribute, Box<QValue>),
+  /// Lambda with its namespace (empty for the root) and its definition.
+  Lambda{
+    context: String,
+    body: String
+  },
+  /// Unary primitive. `Unary(0)` is the general null `(::)`.
+  Unary(u8),
+  /// Operator, e.g., `+`.
+  Operator(u8),
+  /// Iterator, e.g., `'`.
+  Iterator(u8),
+  /// Projection holding the function and arguments.
+  Projection(Vec<QValue>),
+  /// Composition of functions.
+  Composition(Vec<QValue>),
+  /// Function derived by each (`f'`).
+  Each(Box<QValue>),
+  /// Function derived by over (`f/`).
+  Over(Box<QValue>),
+  /// Function derived by scan (`f\`).
+  Scan(Box<QValue>),
+  /// Function derived by each-prior (`f':`).
+  EachPrior(Box<QValue>),
+  /// Function derived by each-right (`f/:`).
+  EachRight(Box<QValue>),
+  /// Function derived by each-left (`f\:`).
+  EachLeft(Box<QValue>),
+  /// Error.
+  Error(String)
+}
+
+/// Elements of q list.
+#[derive(Clone, Debug, PartialEq)]
+pub enum QList{
+  Bool(Vec<bool>),
+  Guid(Vec<[u8; 16]>),
+  Byte(Vec<u8>),
+  Short(Vec<i16>),
+  Int(Vec<i32>),
+  Long(Vec<i64>),
+  Real(Vec<f32>),
+  Float(Vec<f64>),
+  Char(Vec<u8>),
+  Symbol(Vec<String>),
+  Timestamp(Vec<i64>),
+  Month(Vec<i32>),
+  Date(Vec<i32>),
+  Datetime(Vec<f64>),
+  Timespan(Vec<i64>),
+  Minute(Vec<i32>),
+  Second(Vec<i32>),
+  Time(Vec<i32>),
+  /// Mixed list.
+  Mixed(Vec<QValue>)
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Implementation                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+//%% Attribute %%//vvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvv/
+
+impl Attribute{
+  /// Get an attribute from its byte expression. `None` is returned for an unknown byte.
+  pub fn from_byte(byte: u8) -> Option<Self>{
+    match byte{
+      0 => Some(Attribute::None),
+      1 => Some(Attribute::Sorted),
+      2 => Some(Attribute::Unique),
+      3 => Some(Attribute::Parted),
+      4 => Some(Attribute::Grouped),
+      _ => None
+    }
+  }
+}
+
+//%% QValue %%//vvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvv/
+
+impl QValue{
+  /// Copy a q object into Rust memory. Enumerations and mapped lists are resolved into their values as
+  ///  q does when sending them over IPC. Functions other than primitives are decomposed by `value`.
+  ///  Foreign objects cannot be converted and `'type` is returned.
+  /// # Example
+  /// ```no_run
+  /// use kdb_c_api::*;
+  /// use kdb_c_api::ipc::*;
+  ///
+  /// #[no_mangle]
+  /// pub extern "C" fn count_leaves(tree: K) -> K{
+  ///   fn leaves(value: &QValue) -> J{
+  ///     match value{
+  ///       QValue::List(_, QList::Mixed(branches)) => branches.iter().map(leaves).sum(),
+  ///       _ => 1
+  ///     }
+  ///   }
+  ///   match unsafe{QValue::from_k(tree)}{
+  ///     Ok(tree) => unsafe{kj(leaves(&tree))},
+  ///     Err(error) => error.to_q()
+  ///   }
+  /// }
+  /// ```
+  /// ```q
+  /// q)count_leaves: LIBPATH_ (`count_leaves; 1);
+  /// q)count_leaves (1; (`a; "b"); ((2; 3f); 4h))
+  /// 6
+  /// ```
+  /// # Safety
+  /// `qobject` must be null or a valid q object.
+  /// # Note
+  /// This function must be called on the main thread of q.
+  pub unsafe fn from_k(qobject: K) -> Result<Self, KError>{
+    if qobject.is_null(){
+      return Ok(QValue::Unary(0));
+    }
+    let qtype=qobject.get_type();
+    unsafe{
+      Ok(match qtype{
+        -1 => QValue::Bool((*qobject).value.byte != 0),
+        -2 => QValue::Guid(*((*qobject).value.list.G0.as_ptr() as *const [u8; 16])),
+        -4 => QValue::Byte((*qobject).value.byte),
+        -5 => QValue::Short((*qobject).value.short),
+        -6 => QValue::Int((*qobject).value.int),
+        -7 => QValue::Long((*qobject).value.long),
+        -8 => QValue::Real((*qobject).value.real),
+        -9 => QValue::Float((*qobject).value.float),
+        -10 => QValue::Char((*qobject).value.byte),
+        -11 => QValue::Symbol(symbol_to_string((*qobject).value.symbol)),
+        -12 => QValue::Timestamp((*qobject).value.long),
+        -13 => QValue::Month((*qobject).value.int),
+        -14 => QValue::Date((*qobject).value.int),
+        -15 => QValue::Datetime((*qobject).value.float),
+        -16 => QValue::Timespan((*qobject).value.long),
+        -17 => QValue::Minute((*qobject).value.int),
+        -18 => QValue::Second((*qobject).value.int),
+        -19 => QValue::Time((*qobject).value.int),
+        0..=19 => {
+          let attribute=Attribute::from_byte((*qobject).attribute as u8).unwrap_or_default();
+          QValue::List(attribute, QList::from_k(qobject)?)
+        },
+        -76..=-20 | 20..=97 => QValue::from_k(resolve("{$[abs[type x] within 20 76h; value x; x til count x]}", qobject)?.as_k())?,
+        qtype::Table => {
+          let attribute=Attribute::from_byte((*qobject).attribute as u8).unwrap_or_default();
+          QValue::Table(attribute, Box::new(QValue::from_k((*qobject).value.table)?))
+        },
+        qtype::DICTIONARY | qtype::SORTED_DICTIONARY => {
+          let items=qobject.as_mut_slice::<K>();
+          QValue::Dictionary{
+            sorted: qtype == qtype::SORTED_DICTIONARY,
+            keys: Box::new(QValue::from_k(items[0])?),
+            values: Box::new(QValue::from_k(items[1])?)
+          }
+        },
+        qtype::LAMBDA => {
+          let lambda=resolve("{(first value[x] 3; last value x)}", qobject)?;
+          let items=lambda.as_mut_slice::<K>();
+          QValue::Lambda{
+            context: symbol_to_string((*items[0]).value.symbol),
+            body: String::from_utf8_lossy(items[1].as_mut_slice::<G>()).into_owned()
+          }
+        },
+        101 => QValue::Unary((*qobject).value.byte),
+        102 => QValue::Operator((*qobject).value.byte),
+        103 => QValue::Iterator((*qobject).value.byte),
+        104 | 105 => {
+          let functions=resolve("value", qobject)?.as_mut_slice::<K>().iter().map(|&function| QValue::from_k(function)).collect::<Result<Vec<_>, _>>()?;
+          if qtype == 104 {QValue::Projection(functions)} else {QValue::Composition(functions)}
+        },
+        106..=111 => {
+          let function=Box::new(QValue::from_k(resolve("value", qobject)?.as_k())?);
+          match qtype{
+            106 => QValue::Each(function),
+            107 => QValue::Over(function),
+            108 => QValue::Scan(function),
+            109 => QValue::EachPrior(function),
+            110 => QValue::EachRight(function),
+            _ => QValue::EachLeft(function)
+          }
+        },
+        qtype::ERROR => QValue::Error(symbol_to_string((*qobject).value.symbol)),
+        _ => return Err(KError::new("type"))
+      })
+    }
+  }
+
+  /// Get the q type of the value.
+  /// # Example
+  /// ```
+  /// use kdb_c_api::ipc::*;
+  ///
+  /// assert_eq!(QValue::Symbol("a".to_string()).qtype(), -11);
+  /// assert_eq!(QValue::from(vec![1_i64, 2]).qtype(), 7);
+  /// ```
+  pub fn qtype(&self) -> i8{
+    match self{
+      QValue::Bool(_) => -1,
+      QValue::Guid(_) => -2,
+      QValue::Byte(_) => -4,
+      QValue::Short(_) => -5,
+      QValue::Int(_) => -6,
+      QValue::Long(_) => -7,
+      QValue::Real(_) => -8,
+      QValue::Float(_) => -9,
+      QValue::Char(_) => -10,
+      QValue::Symbol(_) => -11,
+      QValue::Timestamp(_) => -12,
+      QValue::Month(_) => -13,
+      QValue::Date(_) => -14,
+      QValue::Datetime(_) => -15,
+      QValue::Timespan(_) => -16,
+      QValue::Minute(_) => -17,
+      QValue::Second(_) => -18,
+      QValue::Time(_) => -19,
+      QValue::List(_, list) => list.qtype(),
+      QValue::Table(_, _) => qtype::Table,
+      QValue::Dictionary{sorted, ..} => if *sorted {qtype::SORTED_DICTIONARY} else {qtype::DICTIONARY},
+      QValue::Lambda{..} => qtype::LAMBDA,
+      QValue::Unary(_) => 101,
+      QValue::Operator(_) => 102,
+      QValue::Iterator(_) => 103,
+      QValue::Projection(_) => 104,
+      QValue::Composition(_) => 105,
+      QValue::Each(_) => 106,
+      QValue::Over(_) => 107,
+      QValue::Scan(_) => 108,
+      QValue::EachPrior(_) => 109,
+      QValue::EachRight(_) => 110,
+      QValue::EachLeft(_) => 111,
+      QValue::Error(_) => qtype::ERROR
+    }
+  }
+
+  /// Create a q object holding the value. Functions are created by `d9` from their serialized bytes.
+  ///  An error value is returned as `KError`.
+  /// # Example
+  /// See the example of [`deserialize`](fn.deserialize.html).
+  /// # Note
+  /// This function must be called on the main thread of q.
+  pub fn to_k(&self) -> Result<KOwned, KError>{
+    unsafe{
+      let qobject=match self{
+        QValue::Bool(bool) => kb(*bool as I),
+        QValue::Guid(guid) => ku(U::new(*guid)),
+        QValue::Byte(byte) => kg(*byte as I),
+        QValue::Short(short) => kh(*short as I),
+        QValue::Int(int) => ki(*int),
+        QValue::Long(long) => kj(*long),
+        QValue::Real(real) => ke(*real as F),
+        QValue::Float(float) => kf(*float),
+        QValue::Char(char) => kc(*char as I),
+        QValue::Symbol(symbol) => ks(intern(symbol)),
+        QValue::Timestamp(nanoseconds) | QValue::Timespan(nanoseconds) => ktj(self.qtype() as I, *nanoseconds),
+        QValue::Date(days) => kd(*days),
+        QValue::Datetime(days) => kz(*days),
+        QValue::Time(milliseconds) => kt(*milliseconds),
+        QValue::Month(int) | QValue::Minute(int) | QValue::Second(int) => int_atom(self.qtype(), *int),
+        QValue::List(attribute, list) => {
+          let list=list.to_k()?;
+          (*list.as_k()).attribute=*attribute as C;
+          list.into_raw()
+        },
+        QValue::Dictionary{sorted, keys, values} => {
+          let keys=keys.to_k()?;
+          let values=values.to_k()?;
+          let dictionary=xD(keys.into_raw(), values.into_raw());
+          if *sorted{
+            (*dictionary).qtype=qtype::SORTED_DICTIONARY;
+          }
+          dictionary
+        },
+        QValue::Table(attribute, dictionary) => {
+          let table=KOwned::from_result(xT(dictionary.to_k()?.into_raw()))?;
+          (*table.as_k()).attribute=*attribute as C;
+          table.into_raw()
+        },
+        QValue::Error(message) => return Err(KError::new(message.as_str())),
+        _ => {
+          // Function
+          let message=encode_message(self, MessageType::Async, Endian::native())?;
+          let bytes=KOwned::from_raw(ktn(qtype::BYTE as I, message.len() as J));
+          bytes.as_mut_slice::<G>().copy_from_slice(&message);
+          return KOwned::from_result(d9(bytes.as_k()));
+        }
+      };
+      Ok(KOwned::from_raw(qobject))
+    }
+  }
+}
+
+macro_rules! impl_from_for_qvalue {
+  ($($rust_type: ty => $atom: ident),*) => {
+    $(
+      impl From<$rust_type> for QValue{
+        fn from(value: $rust_type) -> Self{
+          QValue::$atom(value)
+        }
+      }
+
+      impl From<Vec<$rust_type>> for QValue{
+        fn from(list: Vec<$rust_type>) -> Self{
+          QValue::List(Attribute::None, QList::$atom(list))
+        }
+      }
+    )*
+  };
+}
+
+impl_from_for_qvalue!(bool => Bool, u8 => Byte, i16 => Short, i32 => Int, i64 => Long, f32 => Real, f64 => Float);
+
+impl From<&str> for QValue{
+  /// Rust string is converted into q string (char list).
+  fn from(string: &str) -> Self{
+    QValue::List(Attribute::None, QList::Char(string.as_bytes().to_vec()))
+  }
+}
+
+impl From<String> for QValue{
+  /// Rust string is converted into q string (char list).
+  fn from(string: String) -> Self{
+    QValue::List(Attribute::None, QList::Char(string.into_bytes()))
+  }
+}
+
+impl From<Symbol> for QValue{
+  fn from(symbol: Symbol) -> Self{
+    QValue::Symbol(symbol.0)
+  }
+}
+
+impl From<Vec<Symbol>> for QValue{
+  fn from(symbols: Vec<Symbol>) -> Self{
+    QValue::List(Attribute::None, QList::Symbol(symbols.into_iter().map(|symbol| symbol.0).collect()))
+  }
+}
+
+impl From<Vec<QValue>> for QValue{
+  fn from(list: Vec<QValue>) -> Self{
+    QValue::List(Attribute::None, QList::Mixed(list))
+  }
+}
+
+//%% QList %%//vvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvv/
+
+impl QList{
+  /// Copy elements of a q list whose type is from `0h` to `19h`.
+  /// # Safety
+  /// `qobject` must be a valid q list.
+  unsafe fn from_k(qobject: K) -> Result<Self, KError>{
+    Ok(match qobject.get_type(){
+      qtype::COMPOUND => QList::Mixed(qobject.as_mut_slice::<K>().iter().map(|&item| QValue::from_k(item)).collect::<Result<Vec<_>, _>>()?),
+      qtype::BOOL => QList::Bool(qobject.as_mut_slice::<G>().iter().map(|&bool| bool != 0).collect()),
+      qtype::GUID => QList::Guid(qobject.as_mut_slice::<[u8; 16]>().to_vec()),
+      qtype::BYTE => QList::Byte(qobject.as_mut_slice::<G>().to_vec()),
+      qtype::SHORT => QList::Short(qobject.as_mut_slice::<H>().to_vec()),
+      qtype::INT => QList::Int(qobject.as_mut_slice::<I>().to_vec()),
+      qtype::LONG => QList::Long(qobject.as_mut_slice::<J>().to_vec()),
+      qtype::REAL => QList::Real(qobject.as_mut_slice::<E>().to_vec()),
+      qtype::FLOAT => QList::Float(qobject.as_mut_slice::<F>().to_vec()),
+      qtype::CHAR => QList::Char(qobject.as_mut_slice::<G>().to_vec()),
+      qtype::SYMBOL => QList::Symbol(qobject.as_mut_slice::<S>().iter().map(|&symbol| symbol_to_string(symbol)).collect()),
+      qtype::TIMESTAMP => QList::Timestamp(qobject.as_mut_slice::<J>().to_vec()),
+      qtype::MONTH => QList::Month(qobject.as_mut_slice::<I>().to_vec()),
+      qtype::DATE => QList::Date(qobject.as_mut_slice::<I>().to_vec()),
+      qtype::DATETIME => QList::Datetime(qobject.as_mut_slice::<F>().to_vec()),
+      qtype::TIMESPAN => QList::Timespan(qobject.as_mut_slice::<J>().to_vec()),
+      qtype::MINUTE => QList::Minute(qobject.as_mut_slice::<I>().to_vec()),
+      qtype::SECOND => QList::Second(qobject.as_mut_slice::<I>().to_vec()),
+      qtype::TIME => QList::Time(qobject.as_mut_slice::<I>().to_vec()),
+      _ => return Err(KError::new("type"))
+    })
+  }
+
+  /// Create a q list holding the elements.
+  fn to_k(&self) -> Result<KOwned, KError>{
+    if let QList::Mixed(items)=self{
+      let items=items.iter().map(QValue::to_k).collect::<Result<Vec<_>, _>>()?;
+      let list=unsafe{KOwned::from_raw(ktn(qtype::COMPOUND as I, items.len() as J))};
+      list.as_mut_slice::<K>().iter_mut().zip(items).for_each(|(slot, item)| *slot=item.into_raw());
+      return Ok(list);
+    }
+    let list=unsafe{KOwned::from_raw(ktn(self.qtype() as I, self.len() as J))};
+    match self{
+      QList::Bool(elements) => list.as_mut_slice::<G>().iter_mut().zip(elements).for_each(|(slot, &bool)| *slot=bool as G),
+      QList::Guid(elements) => list.as_mut_slice::<[u8; 16]>().copy_from_slice(elements),
+      QList::Byte(elements) | QList::Char(elements) => list.as_mut_slice::<G>().copy_from_slice(elements),
+      QList::Short(elements) => list.as_mut_slice::<H>().copy_from_slice(elements),
+      QList::Int(elements) | QList::Month(elements) | QList::Date(elements) | QList::Minute(elements) | QList::Second(elements) | QList::Time(elements) => list.as_mut_slice::<I>().copy_from_slice(elements),
+      QList::Long(elements) | QList::Timestamp(elements) | QList::Timespan(elements) => list.as_mut_slice::<J>().copy_from_slice(elements),
+      QList::Real(elements) => list.as_mut_slice::<E>().copy_from_slice(elements),
+      QList::Float(elements) | QList::Datetime(elements) => list.as_mut_slice::<F>().copy_from_slice(elements),
+      QList::Symbol(elements) => list.as_mut_slice::<S>().iter_mut().zip(elements).for_each(|(slot, symbol)| *slot=intern(symbol)),
+      QList::Mixed(_) => unreachable!()
+    }
+    Ok(list)
+  }
+
+  /// Get the q type of the list.
+  pub fn qtype(&self) -> i8{
+    match self{
+      QList::Mixed(_) => qtype::COMPOUND,
+      QList::Bool(_) => qtype::BOOL,
+      QList::Guid(_) => qtype::GUID,
+      QList::Byte(_) => qtype::BYTE,
+      QList::Short(_) => qtype::SHORT,
+      QList::Int(_) => qtype::INT,
+      QList::Long(_) => qtype::LONG,
+      QList::Real(_) => qtype::REAL,
+      QList::Float(_) => qtype::FLOAT,
+      QList::Char(_) => qtype::CHAR,
+      QList::Symbol(_) => qtype::SYMBOL,
+      QList::Timestamp(_) => qtype::TIMESTAMP,
+      QList::Month(_) => qtype::MONTH,
+      QList::Date(_) => qtype::DATE,
+      QList::Datetime(_) => qtype::DATETIME,
+      QList::Timespan(_) => qtype::TIMESPAN,
+      QList::Minute(_) => qtype::MINUTE,
+      QList::Second(_) => qtype::SECOND,
+      QList::Time(_) => qtype::TIME
+    }
+  }
+
+  /// Get the number of elements.
+  pub fn len(&self) -> usize{
+    match self{
+      QList::Mixed(list) => list.len(),
+      QList::Bool(list) => list.len(),
+      QList::Guid(list) => list.len(),
+      QList::Byte(list) | QList::Char(list) => list.len(),
+      QList::Short(list) => list.len(),
+      QList::Int(list) | QList::Month(list) | QList::Date(list) | QList::Minute(list) | QList::Second(list) | QList::Time(list) => list.len(),
+      QList::Long(list) | QList::Timestamp(list) | QList::Timespan(list) => list.len(),
+      QList::Real(list) => list.len(),
+      QList::Float(list) | QList::Datetime(list) => list.len(),
+      QList::Symbol(list) => list.len()
+    }
+  }
+
+  /// Check if the list has no element.
+  pub fn is_empty(&self) -> bool{
+    self.len() == 0
+  }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                              Utility                                 //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Copy a q symbol into a Rust string. Invalid UTF-8 sequences are replaced.
+fn symbol_to_string(symbol: S) -> String{
+  unsafe{CStr::from_ptr(symbol)}.to_string_lossy().into_owned()
+}
+
+/// Create an atom of a type whose underlying value is int, e.g., month.
+unsafe fn int_atom(qtype: i8, int: I) -> K{
+  let atom=ka(qtype as I);
+  (*atom).value.int=int;
+  atom
+}
+
+/// Intern a Rust string as a q symbol. The string is truncated at a null character.
+fn intern(symbol: &str) -> S{
+  let symbol=CString::new(symbol.split('\0').next().unwrap_or_default()).unwrap_or_default();
+  unsafe{ss(symbol.as_ptr() as S)}
+}
+
+/// Apply a q function to a q object which cannot be read directly, e.g., a lambda.
+fn resolve(function: &str, qobject: K) -> Result<KOwned, KError>{
+  eval(function, &[unsafe{KOwned::from_borrowed(qobject)}])
+}
@@ -14,9 +14,44 @@
 //++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
 
 use std::str;
+use std::any::Any;
 use std::ffi::CStr;
 use std::os::raw::{c_char, c_double, c_float, c_int, c_longlong, c_short, c_schar, c_uchar, c_void};
 
+pub mod export;
+pub mod ipc;
+mod owned;
+mod eval;
+mod convert;
+mod global;
+mod callback;
+mod thread;
+#[cfg(unix)]
+mod event;
+#[cfg(all(feature="async", target_os="linux"))]
+mod runtime;
+#[cfg(target_os="linux")]
+mod timer;
+#[cfg(feature="parallel")]
+mod parallel;
+
+pub use owned::{KOwned, KError};
+pub use eval::{eval, apply, LambdaCache, MAX_ARITY};
+pub use convert::{IntoQ, FromQ, Symbol};
+pub use global::{get_global, set_global, upsert_global, global_exists, list_namespace};
+pub use callback::QCallback;
+pub use thread::{SymbolLock, spawn_q_thread};
+#[cfg(unix)]
+pub use event::{register_fd, unregister_fd};
+#[cfg(target_os="linux")]
+pub use event::MainThreadSender;
+#[cfg(all(feature="async", target_os="linux"))]
+pub use runtime::{runtime, spawn_future, Completion};
+#[cfg(target_os="linux")]
+pub use timer::{schedule_timer, schedule_q_timer, cancel_timer, timers, timer_namespace, TimerStatus};
+#[cfg(feature="parallel")]
+pub use parallel::{par_map, par_map_symbol, QElement};
+
 //++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
 //                          Global Variables                            //
 //++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
@@ -94,8 +129,14 @@ pub mod qtype{
   pub const SORTED_DICTIONARY: i8=127;
   /// Type indicator of q error
   pub const ERROR: i8=-128;
+  /// Type indicator of q lambda. Types from 100 to 112 are functions.
+  pub const LAMBDA: i8=100;
   /// Type indicator of q general null
   pub const NULL: i8=101;
+  /// Type indicator of q foreign object.
+  /// - `kK(x)[0]`: destructor
+  /// - `kK(x)[1]`: pointer to the wrapped object
+  pub const FOREIGN: i8=112;
 
 }
 
@@ -147,6 +188,142 @@ macro_rules! str_to_S {
   };
 }
 
+//%% Namespace %%//vvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvv/
+
+/// Build a q namespace, i.e., a dictionary from function names to q functions created by [`dl`](fn.dl.html).
+///  Each function is given as a pair of a function taking `K` objects and its arity. The null symbol mapped to
+///  `(::)` is added at the head so that the dictionary works as a namespace when it is assigned to a global
+///  variable starting with `.`.
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+/// 
+/// #[derive(Default)]
+/// struct OrderBook{
+///   bids: Vec<(f64, i64)>
+/// }
+/// 
+/// extern "C" fn orderbook_new(_: K) -> K{
+///   new_foreign(OrderBook::default())
+/// }
+/// 
+/// foreign_method!{
+///   fn orderbook_add(book: &mut OrderBook, price: K, size: K) -> K{
+///     book.bids.push((price.get_float().unwrap_or(0.0), size.get_long().unwrap_or(0)));
+///     KNULL!()
+///   }
+/// }
+/// 
+/// foreign_method!{
+///   fn orderbook_best(book: &mut OrderBook) -> K{
+///     let best=book.bids.iter().map(|bid| bid.0).fold(f64::NAN, f64::max);
+///     unsafe{kf(best)}
+///   }
+/// }
+/// 
+/// #[no_mangle]
+/// pub extern "C" fn orderbook(_: K) -> K{
+///   namespace!{
+///     "new" => (orderbook_new, 1),
+///     "add" => (orderbook_add, 3),
+///     "best" => (orderbook_best, 1)
+///   }
+/// }
+/// ```
+/// ```q
+/// q).orderbook: LIBPATH_[(`orderbook; 1)][]
+/// q)book: .orderbook.new[]
+/// q).orderbook.add[book; 100.25; 10]
+/// q).orderbook.add[book; 100.5; 3]
+/// q).orderbook.best[book]
+/// 100.5
+/// ```
+#[macro_export]
+macro_rules! namespace {
+  ($($name: expr => ($function: expr, $arity: expr)),* $(,)?) => {{
+    let functions: &[(&str, *const $crate::V, $crate::J)]=&[$(($name, $function as *const $crate::V, $arity)),*];
+    unsafe{
+      let mut keys=$crate::ktn($crate::qtype::SYMBOL as $crate::I, 0);
+      let mut values=$crate::knk(0);
+      // Null symbol mapped to (::) for a namespace
+      $crate::js(&mut keys, $crate::ss($crate::str_to_S!("")));
+      let null=$crate::ka($crate::qtype::NULL as $crate::I);
+      (*null).value.long=0;
+      $crate::jk(&mut values, null);
+      for &(name, function, arity) in functions{
+        $crate::js(&mut keys, $crate::ss($crate::str_to_S!(name)));
+        $crate::jk(&mut values, $crate::dl(function, arity));
+      }
+      $crate::xD(keys, values)
+    }
+  }};
+}
+
+/// Define a function which takes a q foreign object created by [`new_foreign`](fn.new_foreign.html) as the first
+///  argument and other `K` objects as the rest. The first argument is passed to the function body as a mutable
+///  reference to the wrapped object. If the first argument is not a foreign object of the expected type, q error
+///  is returned. The defined function is expected to be passed to [`namespace`](macro.namespace.html).
+/// # Example
+/// See the example of [`namespace`](macro.namespace.html).
+#[macro_export]
+macro_rules! foreign_method {
+  ($(#[$attribute: meta])* $visibility: vis fn $name: ident($this: ident: &mut $object: ty $(, $arg: ident: K)*) -> K $body: block) => {
+    $(#[$attribute])*
+    $visibility extern "C" fn $name(object: $crate::K $(, $arg: $crate::K)*) -> $crate::K{
+      match unsafe{$crate::KUtility::as_foreign::<$object>(&object)}{
+        Ok($this) => $body,
+        Err(error) => unsafe{$crate::krr($crate::null_terminated_str_to_const_S(error))}
+      }
+    }
+  };
+}
+
+//%% Inline q %%//vvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvv/
+
+/// Evaluate a q query with Rust arguments. Each argument is converted into q object by [`IntoQ`](trait.IntoQ.html)
+///  and the result is converted into the inferred Rust type by [`FromQ`](trait.FromQ.html). The query must be a
+///  string literal.
+/// - Without arguments, the query is evaluated as an expression by [`eval`](fn.eval.html) on every call.
+/// - With arguments, the query must be a function. It is parsed only once per call site and cached with
+///   [`LambdaCache`](struct.LambdaCache.html), and then applied to the arguments by [`apply`](fn.apply.html).
+///
+/// q error is returned as [`KError`](struct.KError.html).
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+///
+/// #[no_mangle]
+/// pub extern "C" fn drift(prices: K, days: K) -> K{
+///   let drifted=|| -> Result<Vec<f64>, KError>{
+///     let days: i64=q!("{2*x}", unsafe{KOwned::from_borrowed(days)})?;
+///     q!("{x+y}", days, unsafe{Vec::<f64>::from_q(prices)}?)
+///   };
+///   match drifted(){
+///     Ok(prices) => prices.into_q().into_raw(),
+///     Err(error) => error.to_q()
+///   }
+/// }
+/// ```
+/// ```q
+/// q)drift: LIBPATH_ (`drift; 2);
+/// q)drift[100 101f; 3]
+/// 106 107f
+/// q)drift[100 101f; `three]
+/// 'type
+/// ```
+/// # Note
+/// This macro must be called on the main thread of q.
+#[macro_export]
+macro_rules! q {
+  ($query: literal) => {
+    $crate::eval($query, &[]).and_then(|result| unsafe{$crate::FromQ::from_q(result.as_k())})
+  };
+  ($query: literal $(, $arg: expr)+ $(,)?) => {{
+    static LAMBDA: $crate::LambdaCache=$crate::LambdaCache::new();
+    LAMBDA.apply($query, &[$($crate::IntoQ::into_q($arg)),+]).and_then(|result| unsafe{$crate::FromQ::from_q(result.as_k())})
+  }};
+}
+
 //++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
 //                               Structs                                //
 //++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
@@ -491,6 +668,40 @@ pub trait KUtility{
   /// ```
   fn get_string(&self) -> Result<&str, &'static str>;
 
+  /// Get a Rust object wrapped in a q foreign object created by [`new_foreign`](fn.new_foreign.html).
+  ///  Returns an error if the object is not a foreign object created by this crate or it wraps
+  ///  an object of another type.
+  /// # Safety
+  /// The lifetime of the returned reference is chosen by the caller. No other reference to the same foreign
+  ///  object may be alive while it is used, and it must not outlive the foreign object freed by q.
+  /// # Example
+  /// ```no_run
+  /// use kdb_c_api::*;
+  /// 
+  /// struct Pilgrimage{
+  ///   steps: i64
+  /// }
+  /// 
+  /// #[no_mangle]
+  /// pub extern "C" fn walk(pilgrimage: K, steps: K) -> K{
+  ///   match unsafe{pilgrimage.as_foreign::<Pilgrimage>()}{
+  ///     Ok(pilgrimage) => {
+  ///       pilgrimage.steps+=steps.get_long().unwrap_or(0);
+  ///       unsafe{kj(pilgrimage.steps)}
+  ///     },
+  ///     Err(error) => unsafe{krr(null_terminated_str_to_const_S(error))}
+  ///   }
+  /// }
+  /// ```
+  /// ```q
+  /// q)walk: LIBPATH_ (`walk; 2);
+  /// q)walk[pilgrimage; 3]
+  /// 3
+  /// q)walk[pilgrimage; 4]
+  /// 7
+  /// ```
+  unsafe fn as_foreign<'a, T: 'static>(&self) -> Result<&'a mut T, &'static str>;
+
   /// Get a length of the list. More specifically, a value of `k0.value.list.n` for list types.
   ///  Otherwise 2 for table and 1 for atom and null.
   /// # Example
@@ -611,6 +822,23 @@ impl KUtility for K{
     }
   }
 
+  unsafe fn as_foreign<'a, T: 'static>(&self) -> Result<&'a mut T, &'static str>{
+    match (**self).qtype{
+      qtype::FOREIGN => {
+        let inner=self.as_mut_slice::<*mut V>();
+        if inner.len() != 2 || inner[0] != drop_foreign as *mut V{
+          // Foreign object created by another library.
+          return Err("unknown foreign\0");
+        }
+        match (*(inner[1] as *mut Box<dyn Any>)).downcast_mut::<T>(){
+          Some(object) => Ok(object),
+          None => Err("foreign type mismatch\0")
+        }
+      },
+      _ => Err("not a foreign\0")
+    }
+  }
+
   fn len(&self) -> i64{
     unsafe{
       if (**self).qtype < 0 || (**self).qtype == qtype::NULL{
@@ -1515,6 +1743,29 @@ extern "C"{
   /// ```
   pub fn dj(days: I) -> I;
 
+  /// Function takes a C function that would take `n` `K` objects as arguments and returns a `K` object.
+  ///  Returns a q function. Functions which are not exported can be passed to q in this way.
+  /// # Example
+  /// ```no_run
+  /// use kdb_c_api::*;
+  /// 
+  /// extern "C" fn add_one(x: K) -> K{
+  ///   unsafe{kj(x.get_long().unwrap_or(0) + 1)}
+  /// }
+  /// 
+  /// #[no_mangle]
+  /// pub extern "C" fn give_me_function(_: K) -> K{
+  ///   unsafe{dl(add_one as *const V, 1)}
+  /// }
+  /// ```
+  /// ```q
+  /// q)give_me_function: LIBPATH_ (`give_me_function; 1);
+  /// q)add_one: give_me_function[]
+  /// q)add_one 41
+  /// 42
+  /// ```
+  pub fn dl(func: *const V, n: J) -> K;
+
   /* Unsupported
 
   /// Connect with timeout (millisecond) and capability. The value of capability is:
@@ -1553,7 +1804,7 @@ extern "C"{
   ///  The message is not modified.
   ///  Returns `0` if not valid.
   /// # Note
-  /// Decompressed data only.
+  /// Decompressed data only. Use [`ipc::is_valid_message`](ipc/fn.is_valid_message.html) instead.
   pub fn okx(bytes: K) -> I;
 
   /// Return a dictionary of TLS setting. See `-26!`.
@@ -1566,13 +1817,6 @@ extern "C"{
   /// This function seems not exist (`undefined symbol`).
   pub fn ver() -> I;
 
-  /// Function takes a C function that would take `n` `K` objects as arguments and returns a `K` object.
-  ///  Returns a q function.
-  /// # Note
-  /// As this library is purposed to build shared object, this function will not add a value. User can
-  ///  use `2:` instead.
-  pub fn dl(func: *const V, n: J) -> K;
-  
   /// Variadic version of `knk`.
   fn vaknk(qtype: I, args: va_list) -> K;
 
@@ -1680,6 +1924,57 @@ pub fn null_terminated_str_to_const_S(string: &str) -> const_S {
 
 //%% Constructor %%//vvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvv/
 
+/// Create a q foreign object wrapping a Rust object. The object is dropped when the reference
+///  count of the foreign object reaches zero on q side. The wrapped object can be accessed with
+///  [`as_foreign`](trait.KUtility.html#tymethod.as_foreign).
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+/// 
+/// struct Pilgrimage{
+///   steps: i64
+/// }
+/// 
+/// #[no_mangle]
+/// pub extern "C" fn create_pilgrimage(_: K) -> K{
+///   new_foreign(Pilgrimage{steps: 0})
+/// }
+/// ```
+/// ```q
+/// q)create_pilgrimage: LIBPATH_ (`create_pilgrimage; 1);
+/// q)pilgrimage: create_pilgrimage[]
+/// q)type pilgrimage
+/// 112h
+/// ```
+pub fn new_foreign<T: 'static>(object: T) -> K{
+  let boxed: Box<Box<dyn Any>>=Box::new(Box::new(object));
+  unsafe{
+    let foreign=knk(2, drop_foreign as *mut V, Box::into_raw(boxed) as *mut V);
+    (*foreign).qtype=qtype::FOREIGN;
+    foreign
+  }
+}
+
+/// Check if a q object is a foreign object created by [`new_foreign`](fn.new_foreign.html), not a function
+///  loaded by `2:` or a foreign object of another library which are also `112h`.
+/// # Safety
+/// `qobject` must be a valid q object.
+pub(crate) unsafe fn is_rust_foreign(qobject: K) -> bool{
+  if (*qobject).qtype != qtype::FOREIGN{
+    return false;
+  }
+  let inner=qobject.as_mut_slice::<*mut V>();
+  inner.len() == 2 && inner[0] == drop_foreign as *mut V
+}
+
+/// Destructor of a foreign object called by q when its reference count reaches zero.
+extern "C" fn drop_foreign(foreign: K) -> K{
+  unsafe{
+    drop(Box::from_raw(foreign.as_mut_slice::<*mut V>()[1] as *mut Box<dyn Any>));
+  }
+  KNULL!()
+}
+
 /// Create a month object. This is a complememtal constructor of
 ///  missing month type.
 /// # Example
@@ -0,0 +1,166 @@
+//! This module provides `KOwned`, a q object owning one reference count, and `KError`, an error
+//!  returned by q or raised by the safe wrappers of this crate.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Load Libraries                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use super::*;
+use std::ffi::CString;
+use std::fmt;
+use std::ops::Deref;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                               Structs                                //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// q object owning one reference count. The reference is released by `r0` when it is dropped.
+///  Methods of [`KUtility`](trait.KUtility.html) can be called through dereference.
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+///
+/// #[no_mangle]
+/// pub extern "C" fn idle_man2(_: K) -> K{
+///   // Created an int object but changed the mind. Discarded with `r0`.
+///   let _int=unsafe{KOwned::from_raw(ki(777))};
+///   KNULL!()
+/// }
+/// ```
+#[derive(Debug)]
+pub struct KOwned(K);
+
+/// Error returned by q or raised by the safe wrappers of this crate.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KError{
+  /// Error message.
+  message: String,
+  /// Backtrace of q if available.
+  backtrace: Option<String>
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Implementation                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+//%% KOwned %%//vvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvv/
+
+impl KOwned{
+  /// Take over a reference of q object, e.g., an object returned by a constructor or `k`.
+  /// # Safety
+  /// `qobject` must be a valid non-null q object whose reference is not released by anyone else.
+  pub unsafe fn from_raw(qobject: K) -> Self{
+    KOwned(qobject)
+  }
+
+  /// Increment a reference count of a borrowed q object, e.g., an argument passed from q, and own it.
+  /// # Safety
+  /// `qobject` must be a valid non-null q object.
+  /// # Example
+  /// See the example of [`eval`](fn.eval.html).
+  pub unsafe fn from_borrowed(qobject: K) -> Self{
+    KOwned(r1(qobject))
+  }
+
+  /// Convert a result of q evaluation into `KOwned`. A null pointer or an error object is converted into `KError`.
+  /// # Safety
+  /// `result` must be null or a valid q object whose reference is not released by anyone else.
+  pub unsafe fn from_result(result: K) -> Result<Self, KError>{
+    if result.is_null(){
+      Err(KError::new("evaluation failed"))
+    }
+    else if (*result).qtype == qtype::ERROR{
+      let error=KError::new(S_to_str((*result).value.symbol));
+      r0(result);
+      Err(error)
+    }
+    else{
+      Ok(KOwned(result))
+    }
+  }
+
+  /// Get the underlying q object without releasing the reference.
+  pub fn as_k(&self) -> K{
+    self.0
+  }
+
+  /// Give up the ownership and get the underlying q object, e.g., to return it to q.
+  pub fn into_raw(self) -> K{
+    let qobject=self.0;
+    std::mem::forget(self);
+    qobject
+  }
+}
+
+impl Deref for KOwned{
+  type Target=K;
+  fn deref(&self) -> &K{
+    &self.0
+  }
+}
+
+impl Clone for KOwned{
+  fn clone(&self) -> Self{
+    unsafe{KOwned::from_borrowed(self.0)}
+  }
+}
+
+impl Drop for KOwned{
+  fn drop(&mut self){
+    unsafe{r0(self.0)};
+  }
+}
+
+//%% KError %%//vvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvv/
+
+impl KError{
+  /// Create an error with a message.
+  pub fn new<T: Into<String>>(message: T) -> Self{
+    KError{message: message.into(), backtrace: None}
+  }
+
+  /// Create an error with a message and a backtrace of q.
+  pub fn with_backtrace<T: Into<String>, U: Into<String>>(message: T, backtrace: U) -> Self{
+    KError{message: message.into(), backtrace: Some(backtrace.into())}
+  }
+
+  /// Get the error message.
+  pub fn message(&self) -> &str{
+    &self.message
+  }
+
+  /// Get the backtrace of q if available.
+  pub fn backtrace(&self) -> Option<&str>{
+    self.backtrace.as_deref()
+  }
+
+  /// Convert into q error object to return to q. The message is interned as a symbol so that
+  ///  it remains valid after the error is dropped.
+  /// # Example
+  /// See the example of [`eval`](fn.eval.html).
+  pub fn to_q(&self) -> K{
+    let message=CString::new(self.message.replace('\0', "")).unwrap_or_default();
+    unsafe{krr(ss(message.as_ptr() as S))}
+  }
+}
+
+impl From<&str> for KError{
+  /// Errors of [`KUtility`](trait.KUtility.html) are null-terminated. The null character is trimmed.
+  fn from(message: &str) -> Self{
+    KError::new(message.trim_end_matches('\0'))
+  }
+}
+
+impl From<String> for KError{
+  fn from(message: String) -> Self{
+    KError::new(message)
+  }
+}
+
+impl fmt::Display for KError{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+    write!(f, "{}", self.message)
+  }
+}
+
+impl std::error::Error for KError{}
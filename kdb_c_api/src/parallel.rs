@@ -0,0 +1,150 @@
+//! This module provides parallel kernels applying a Rust function over a q simple list with Rayon.
+//!  Enabled with `parallel` feature. The input list is split into chunks processed by the Rayon thread
+//!  pool without copying and the results are written into a q list allocated on the calling thread.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Load Libraries                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use super::*;
+use crate::convert::symbol_to_cstring;
+use rayon::prelude::*;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                          Global Variables                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Number of elements processed by a task at least.
+const MIN_CHUNK_LENGTH: usize=4096;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                                Traits                                //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Rust type of an element of q simple list.
+pub trait QElement: Copy + Send + Sync + 'static{
+  /// q list types whose elements are this type. The first one is used for a result list.
+  const QTYPES: &'static [i8];
+}
+
+impl QElement for G{
+  // Bool and char lists are excluded since a function from `G` to `G` could write values out of their range
+  //  into the result of the same type, e.g., `2` into a bool list.
+  const QTYPES: &'static [i8]=&[qtype::BYTE];
+}
+
+impl QElement for H{
+  const QTYPES: &'static [i8]=&[qtype::SHORT];
+}
+
+impl QElement for I{
+  const QTYPES: &'static [i8]=&[qtype::INT, qtype::MONTH, qtype::DATE, qtype::MINUTE, qtype::SECOND, qtype::TIME];
+}
+
+impl QElement for J{
+  const QTYPES: &'static [i8]=&[qtype::LONG, qtype::TIMESTAMP, qtype::TIMESPAN];
+}
+
+impl QElement for E{
+  const QTYPES: &'static [i8]=&[qtype::REAL];
+}
+
+impl QElement for F{
+  const QTYPES: &'static [i8]=&[qtype::FLOAT, qtype::DATETIME];
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                                Kernel                                //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Apply a function to each element of a q simple list in parallel and return a new list of the results.
+///  `'type` is returned if the list is not a list of `T`. The type of the result list is the type of the input
+///  list if it is a list of `U`, e.g., timestamp for a function from `J` to `J`; otherwise the first type of
+///  [`QElement::QTYPES`](trait.QElement.html#associatedconstant.QTYPES) of `U`.
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+///
+/// #[no_mangle]
+/// pub extern "C" fn discount(prices: K, rate: K) -> K{
+///   let rate=match rate.get_float(){
+///     Ok(rate) => rate,
+///     Err(error) => return unsafe{krr(null_terminated_str_to_const_S(error))}
+///   };
+///   match par_map(prices, |price: F| price * (1.0 - rate)){
+///     Ok(discounted) => discounted.into_raw(),
+///     Err(error) => error.to_q()
+///   }
+/// }
+/// ```
+/// ```q
+/// q)discount: LIBPATH_ (`discount; 2);
+/// q)discount[100 200 300f; 0.1]
+/// 90 180 270f
+/// ```
+/// # Note
+/// This function must be called on the main thread of q. The function is called on threads of Rayon and
+///  must not touch q objects.
+pub fn par_map<T, U, F>(list: K, function: F) -> Result<KOwned, KError> where T: QElement, U: QElement, F: Fn(T) -> U + Send + Sync{
+  let input=typed_slice::<T>(list)?;
+  let input_type=list.get_type();
+  let output_type=if U::QTYPES.contains(&input_type) {input_type} else {U::QTYPES[0]};
+  let output=unsafe{KOwned::from_raw(ktn(output_type as I, input.len() as J))};
+  output.as_mut_slice::<U>().par_chunks_mut(MIN_CHUNK_LENGTH).zip(input.par_chunks(MIN_CHUNK_LENGTH)).for_each(|(output, input)| {
+    output.iter_mut().zip(input).for_each(|(output, &input)| *output=function(input));
+  });
+  Ok(output)
+}
+
+/// Apply a function producing a string to each element of a q simple list in parallel and return a new symbol
+///  list. A string is truncated at a null character like [`Symbol`](struct.Symbol.html). The strings are
+///  interned on threads of Rayon while the symbol lock is held by [`SymbolLock`](struct.SymbolLock.html).
+///  `'type` is returned if the list is not a list of `T`.
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+///
+/// #[no_mangle]
+/// pub extern "C" fn name_tribes(ids: K) -> K{
+///   match par_map_symbol(ids, |id: J| format!("tribe{}", id)){
+///     Ok(names) => names.into_raw(),
+///     Err(error) => error.to_q()
+///   }
+/// }
+/// ```
+/// ```q
+/// q)name_tribes: LIBPATH_ (`name_tribes; 1);
+/// q)name_tribes[til 3]
+/// `tribe0`tribe1`tribe2
+/// ```
+/// # Note
+/// This function must be called on the main thread of q. The function is called on threads of Rayon and
+///  must not touch q objects.
+pub fn par_map_symbol<T, F>(list: K, function: F) -> Result<KOwned, KError> where T: QElement, F: Fn(T) -> String + Send + Sync{
+  let input=typed_slice::<T>(list)?;
+  let output=unsafe{KOwned::from_raw(ktn(qtype::SYMBOL as I, input.len() as J))};
+  // Symbol is sent across threads as an address.
+  let symbols=output.as_mut_slice::<usize>();
+  let _lock=SymbolLock::lock();
+  symbols.par_chunks_mut(MIN_CHUNK_LENGTH).zip(input.par_chunks(MIN_CHUNK_LENGTH)).for_each(|(output, input)| {
+    output.iter_mut().zip(input).for_each(|(output, &input)| {
+      let symbol=symbol_to_cstring(function(input));
+      *output=unsafe{ss(symbol.as_ptr() as S)} as usize;
+    });
+  });
+  Ok(output)
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                              Utility                                 //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Get elements of a q simple list as a slice after checking the type.
+fn typed_slice<'a, T: QElement>(list: K) -> Result<&'a [T], KError>{
+  if T::QTYPES.contains(&list.get_type()){
+    Ok(list.as_mut_slice::<T>())
+  }
+  else{
+    Err(KError::new("type"))
+  }
+}
@@ -0,0 +1,189 @@
+//! This module provides integration of Rust async runtime (`tokio`) with q. Enabled with `async` feature.
+//!  Futures are driven by a runtime running on background threads and their results are delivered to
+//!  the main thread of q by [`MainThreadSender`](../struct.MainThreadSender.html), where a q callback is
+//!  invoked or a deferred response is sent with `-30!`.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Load Libraries                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use super::*;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::runtime::{Builder, Runtime};
+use tokio::task::AbortHandle;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                          Global Variables                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Result of a future sent to the main thread with its task ID. The value is converted into q object
+///  on the main thread.
+type Delivery=(u64, Result<Box<dyn FnOnce() -> KOwned + Send>, KError>);
+
+/// Runtime driving futures.
+static RUNTIME: OnceLock<Runtime>=OnceLock::new();
+
+/// Channel delivering results of futures to the main thread.
+static DELIVERY: OnceLock<MainThreadSender<Delivery>>=OnceLock::new();
+
+/// ID of the next task.
+static NEXT_TASK: AtomicU64=AtomicU64::new(0);
+
+thread_local!{
+  /// Destinations of results of running futures keyed by task ID. Only accessed on the main thread.
+  static COMPLETIONS: RefCell<BTreeMap<u64, Completion>>=const {RefCell::new(BTreeMap::new())};
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                               Structs                                //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Destination of a result of a future spawned by [`spawn_future`](fn.spawn_future.html).
+#[derive(Debug)]
+pub enum Completion{
+  /// q function taking a success flag and a result, i.e., `{[ok; result] ...}`. The result is an error
+  ///  message (string) on failure.
+  Callback(QCallback),
+  /// Handle of a client whose synchronous request was deferred by `-30!(::)`. The result is sent back with
+  ///  `-30!(handle; isError; result)`. If the future is cancelled, the client receives `'cancelled`.
+  Deferred(I)
+}
+
+/// Token cancelling a future when it is dropped. Wrapped in a foreign object returned to q.
+struct CancelToken{
+  task: u64,
+  abort: AbortHandle
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Implementation                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+impl Completion{
+  /// Create a callback destination from a q function. `'rank` is returned if the function does not take
+  ///  two arguments.
+  /// # Safety
+  /// `function` must be a valid q object.
+  /// # Note
+  /// This function must be called on the main thread of q.
+  pub unsafe fn callback(function: K) -> Result<Self, KError>{
+    Ok(Completion::Callback(QCallback::new(function, 2)?))
+  }
+}
+
+impl Drop for CancelToken{
+  fn drop(&mut self){
+    // Called by q on the main thread. Nothing happens if the future has completed.
+    self.abort.abort();
+    // A callback is dropped without being called while a deferred client must not wait forever.
+    if let Some(Completion::Deferred(handle))=COMPLETIONS.with(|completions| completions.borrow_mut().remove(&self.task)){
+      if let Err(error)=respond(handle, false, "cancelled".into_q()){
+        eprintln!("kdb_c_api: failed to cancel a deferred response: {}", error);
+      }
+    }
+  }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                                 Task                                 //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Get the runtime driving futures. The multi-thread runtime is started on the first call.
+pub fn runtime() -> &'static Runtime{
+  RUNTIME.get_or_init(|| {
+    Builder::new_multi_thread().thread_name("kdb_c_api-async").enable_all().build().expect("failed to start async runtime")
+  })
+}
+
+/// Spawn a future on [`runtime`](fn.runtime.html) and return immediately with a cancellation token
+///  (foreign object). When the future completes, the result is delivered to the main thread of q and
+///  passed to the destination. The future is cancelled when the token is released on q side; therefore
+///  q must hold the token until the result arrives. The callback of a cancelled future is not called and
+///  a deferred client receives `'cancelled`. To reply to a synchronous request asynchronously,
+///  call this function in `.z.pg` with [`Completion::Deferred`](enum.Completion.html#variant.Deferred)
+///  and return `-30!(::)`.
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+/// use std::time::Duration;
+///
+/// #[no_mangle]
+/// pub extern "C" fn prophesy(days: K, callback: K) -> K{
+///   let days=match days.get_long(){
+///     Ok(days) => days,
+///     Err(error) => return unsafe{krr(null_terminated_str_to_const_S(error))}
+///   };
+///   let completion=match unsafe{Completion::callback(callback)}{
+///     Ok(completion) => completion,
+///     Err(error) => return error.to_q()
+///   };
+///   let prophecy=async move {
+///     // 1 day = 1 millisecond
+///     tokio::time::sleep(Duration::from_millis(days.max(0) as u64)).await;
+///     if days < 0 {Err(KError::new("no prophecy for the past"))} else {Ok(Symbol(format!("in {} days", days)))}
+///   };
+///   match spawn_future(prophecy, completion){
+///     Ok(token) => token.into_raw(),
+///     Err(error) => error.to_q()
+///   }
+/// }
+/// ```
+/// ```q
+/// q)prophesy: LIBPATH_ (`prophesy; 2);
+/// q)token: prophesy[40; {[ok; result] show (ok; result)}]
+/// q)// After 40 milliseconds
+/// 1b
+/// `in 40 days
+/// ```
+/// # Note
+/// This function must be called on the main thread of q.
+pub fn spawn_future<F, T>(future: F, completion: Completion) -> Result<KOwned, KError>
+  where F: Future<Output=Result<T, KError>> + Send + 'static, T: IntoQ + Send + 'static{
+  let sender=match DELIVERY.get(){
+    Some(sender) => sender,
+    None => {
+      let sender=MainThreadSender::new(deliver)?;
+      DELIVERY.get_or_init(|| sender)
+    }
+  }.clone();
+  let task=NEXT_TASK.fetch_add(1, Ordering::Relaxed);
+  COMPLETIONS.with(|completions| completions.borrow_mut().insert(task, completion));
+  let handle=runtime().spawn(async move {
+    let result=future.await.map(|value| Box::new(move || value.into_q()) as Box<dyn FnOnce() -> KOwned + Send>);
+    // Channel is never closed.
+    let _=sender.send((task, result));
+  });
+  Ok(unsafe{KOwned::from_raw(new_foreign(CancelToken{task, abort: handle.abort_handle()}))})
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                              Utility                                 //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Pass a result of a future to its destination on the main thread. The result of a cancelled future is dropped.
+fn deliver((task, result): Delivery){
+  let completion=match COMPLETIONS.with(|completions| completions.borrow_mut().remove(&task)){
+    Some(completion) => completion,
+    None => return
+  };
+  let (ok, payload)=match result{
+    Ok(value) => (true, value()),
+    Err(error) => (false, error.message().into_q())
+  };
+  let delivered=match completion{
+    Completion::Callback(callback) => callback.call(&[ok.into_q(), payload]).map(|_| ()),
+    Completion::Deferred(handle) => respond(handle, ok, payload)
+  };
+  if let Err(error)=delivered{
+    eprintln!("kdb_c_api: failed to deliver a result of future: {}", error);
+  }
+}
+
+/// Send a deferred response to a client by `-30!`. `payload` is an error message if `ok` is `false`.
+fn respond(handle: I, ok: bool, payload: KOwned) -> Result<(), KError>{
+  q!("{[handle; error; result] -30!(handle; error; result)}", handle, !ok, payload)
+}
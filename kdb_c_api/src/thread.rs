@@ -0,0 +1,93 @@
+//! This module provides helpers for threads touching q memory: a guard of the symbol lock set by
+//!  [`setm`](../fn.setm.html) and a thread releasing its memory pool by [`m9`](../fn.m9.html) on exit.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Load Libraries                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use super::*;
+use std::thread::{self, JoinHandle};
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                               Structs                                //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Guard enabling the lock of symbol interning by `setm(1)`. The previous setting is restored when
+///  the guard is dropped, including when a thread panics.
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+///
+/// #[no_mangle]
+/// pub extern "C" fn parallel_sym_change2(list: K) -> K{
+///   // `K` cannot have `Send` because it is a pointer but `k0` does.
+///   let mut inner=unsafe{*list};
+///   // Lock symbol before creating an internal symbol on another thread.
+///   let _lock=SymbolLock::lock();
+///   let task=spawn_q_thread(move || {
+///     inner.as_mut_slice::<S>()[0]=unsafe{ss(str_to_S!("replaced"))};
+///     inner
+///   });
+///   list.as_mut_slice::<S>()[1]=unsafe{ss(str_to_S!("symbolbol"))};
+///   match task.join(){
+///     Ok(replaced) => unsafe{
+///       *list=replaced;
+///       // Increment reference count for copy.
+///       r1(list)
+///     },
+///     Err(_) => KError::new("oh no").to_q()
+///   }
+///   // Unlocked here.
+/// }
+/// ```
+/// ```q
+/// q)paradise: LIBPATH_ (`parallel_sym_change2; 1);
+/// q)paradise[`a`b]
+/// `replaced`symbolbol
+/// ```
+#[derive(Debug)]
+pub struct SymbolLock{
+  /// Setting of `setm` before the lock.
+  previous: I
+}
+
+/// Guard calling `m9` when a thread spawned by `spawn_q_thread` exits.
+struct MemoryPool;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Implementation                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+impl SymbolLock{
+  /// Enable the lock of symbol interning until the guard is dropped.
+  pub fn lock() -> Self{
+    SymbolLock{previous: unsafe{setm(1)}}
+  }
+}
+
+impl Drop for SymbolLock{
+  fn drop(&mut self){
+    unsafe{setm(self.previous)};
+  }
+}
+
+impl Drop for MemoryPool{
+  fn drop(&mut self){
+    unsafe{m9()};
+  }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                                Thread                                //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Spawn a thread which can allocate q objects. The memory pool of the thread is released by `m9`
+///  when the closure returns or panics. Symbols must be interned under [`SymbolLock`](struct.SymbolLock.html).
+/// # Example
+/// See the example of [`SymbolLock`](struct.SymbolLock.html).
+pub fn spawn_q_thread<F, T>(function: F) -> JoinHandle<T> where F: FnOnce() -> T + Send + 'static, T: Send + 'static{
+  thread::spawn(move || {
+    let _pool=MemoryPool;
+    function()
+  })
+}
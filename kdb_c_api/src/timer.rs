@@ -0,0 +1,239 @@
+//! This module provides timers running on the main thread of q independently of `.z.ts`. Each timer is
+//!  backed by a `timerfd` hooked to the main event loop of q by [`register_fd`](../fn.register_fd.html)
+//!  and fires at nanosecond resolution. A timer invokes either a Rust closure or a q function.
+//!
+//! Timers can be also operated from q through the namespace returned by
+//!  [`timer_namespace`](../fn.timer_namespace.html).
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Load Libraries                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use super::*;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::os::unix::io::RawFd;
+use std::time::Duration;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                          Global Variables                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+thread_local!{
+  /// Scheduled timers keyed by timer ID. Only accessed on the main thread.
+  static TIMERS: RefCell<BTreeMap<J, Entry>>=const {RefCell::new(BTreeMap::new())};
+  /// ID of the next timer.
+  static NEXT_TIMER: Cell<J>=const {Cell::new(1)};
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                               Structs                                //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Status of a scheduled timer returned by [`timers`](fn.timers.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimerStatus{
+  /// Timer ID.
+  pub id: J,
+  /// Interval of a repeating timer. `None` for a one-shot timer.
+  pub interval: Option<Duration>,
+  /// Time until the timer fires next.
+  pub remaining: Duration
+}
+
+/// Registered timer.
+struct Entry{
+  /// `timerfd` of the timer.
+  fd: RawFd,
+  /// Interval of a repeating timer.
+  interval: Option<Duration>
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                                Timer                                 //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Schedule a closure to be called on the main thread of q after `delay`, and then every `interval` if it is
+///  given. The closure receives the timer ID and can cancel the timer itself. A one-shot timer is removed after
+///  it fires. Return the timer ID.
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+/// use std::time::Duration;
+///
+/// #[no_mangle]
+/// pub extern "C" fn blow_trumpets(_: K) -> K{
+///   // Blow seven times every 500 microseconds.
+///   let mut blown=0;
+///   let scheduled=schedule_timer(Duration::from_micros(500), Some(Duration::from_micros(500)), move |id| {
+///     blown+=1;
+///     println!("trumpet {}", blown);
+///     if blown == 7{
+///       cancel_timer(id);
+///     }
+///   });
+///   match scheduled{
+///     Ok(id) => unsafe{kj(id)},
+///     Err(error) => error.to_q()
+///   }
+/// }
+/// ```
+/// # Note
+/// This function must be called on the main thread of q.
+pub fn schedule_timer<F>(delay: Duration, interval: Option<Duration>, mut callback: F) -> Result<J, KError> where F: FnMut(J) + 'static{
+  let fd=unsafe{libc::timerfd_create(libc::CLOCK_MONOTONIC, libc::TFD_NONBLOCK | libc::TFD_CLOEXEC)};
+  if fd == -1{
+    return Err(KError::new(format!("failed to create timerfd: {}", std::io::Error::last_os_error())));
+  }
+  // Zero value disarms timerfd.
+  let interval=interval.filter(|interval| !interval.is_zero());
+  let setting=libc::itimerspec{
+    it_interval: to_timespec(interval.unwrap_or_default()),
+    it_value: to_timespec(delay.max(Duration::from_nanos(1)))
+  };
+  if unsafe{libc::timerfd_settime(fd, 0, &setting, std::ptr::null_mut())} == -1{
+    let error=std::io::Error::last_os_error();
+    unsafe{libc::close(fd)};
+    return Err(KError::new(format!("failed to set timerfd: {}", error)));
+  }
+  let id=NEXT_TIMER.with(|next| next.replace(next.get() + 1));
+  let registered=register_fd(fd, move |fd| {
+    // Reset the number of expirations.
+    let mut expirations=0_u64;
+    unsafe{libc::read(fd, &mut expirations as *mut u64 as *mut libc::c_void, 8)};
+    callback(id);
+    if interval.is_none(){
+      cancel_timer(id);
+    }
+    None
+  });
+  if let Err(error)=registered{
+    unsafe{libc::close(fd)};
+    return Err(error);
+  }
+  TIMERS.with(|timers| timers.borrow_mut().insert(id, Entry{fd, interval}));
+  Ok(id)
+}
+
+/// Schedule a q function to be called on the main thread of q after `delay`, and then every `interval` if it is
+///  given. The function receives the timer ID. q error raised by the function is printed to stderr.
+/// # Note
+/// This function must be called on the main thread of q.
+pub fn schedule_q_timer(delay: Duration, interval: Option<Duration>, callback: QCallback) -> Result<J, KError>{
+  if callback.arity() != 1{
+    return Err(KError::new("rank"));
+  }
+  schedule_timer(delay, interval, move |id| {
+    if let Err(error)=callback.call(&[id.into_q()]){
+      eprintln!("kdb_c_api: timer {} failed: {}", id, error);
+    }
+  })
+}
+
+/// Cancel a timer. Return `false` if the timer does not exist.
+/// # Note
+/// This function must be called on the main thread of q.
+pub fn cancel_timer(id: J) -> bool{
+  match TIMERS.with(|timers| timers.borrow_mut().remove(&id)){
+    Some(entry) => unregister_fd(entry.fd, true),
+    None => false
+  }
+}
+
+/// List scheduled timers in the order of timer ID.
+/// # Note
+/// This function must be called on the main thread of q.
+pub fn timers() -> Vec<TimerStatus>{
+  TIMERS.with(|timers| {
+    timers.borrow().iter().map(|(&id, entry)| {
+      let mut setting=libc::itimerspec{it_interval: to_timespec(Duration::ZERO), it_value: to_timespec(Duration::ZERO)};
+      unsafe{libc::timerfd_gettime(entry.fd, &mut setting)};
+      let remaining=Duration::new(setting.it_value.tv_sec as u64, setting.it_value.tv_nsec as u32);
+      TimerStatus{id, interval: entry.interval, remaining}
+    }).collect()
+  })
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                                q API                                 //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Build a q namespace operating timers:
+/// - `schedule[delay; interval; function]`: Schedule a q function taking a timer ID. `delay` and `interval`
+///   are timespan or long (nanoseconds). `interval` of `0` or null makes a one-shot timer. Return the timer ID.
+/// - `list[]`: Return a table of scheduled timers with columns `id`, `interval` and `remaining`.
+/// - `cancel[id]`: Cancel a timer. Return `0b` if the timer does not exist.
+/// # Example
+/// ```no_run
+/// use kdb_c_api::*;
+///
+/// #[no_mangle]
+/// pub extern "C" fn timer(_: K) -> K{
+///   timer_namespace()
+/// }
+/// ```
+/// ```q
+/// q).timer: LIBPATH_[(`timer; 1)][]
+/// q)id: .timer.schedule[0D00:00:00.000500; 0D00:00:01; {[id] show .z.p}]
+/// q).timer.list[]
+/// id interval             remaining
+/// ----------------------------------------------
+/// 1  0D00:00:01.000000000 0D00:00:00.999321000
+/// q).timer.cancel[id]
+/// 1b
+/// ```
+pub fn timer_namespace() -> K{
+  namespace!{
+    "schedule" => (q_schedule_timer, 3),
+    "list" => (q_timers, 1),
+    "cancel" => (q_cancel_timer, 1)
+  }
+}
+
+/// `schedule` of [`timer_namespace`](fn.timer_namespace.html).
+extern "C" fn q_schedule_timer(delay: K, interval: K, function: K) -> K{
+  let scheduled=(|| -> Result<J, KError>{
+    let delay=delay.get_long()?;
+    let interval=interval.get_long()?;
+    let callback=unsafe{QCallback::new(function, 1)}?;
+    let interval=if interval <= 0 {None} else {Some(Duration::from_nanos(interval as u64))};
+    schedule_q_timer(Duration::from_nanos(delay.max(0) as u64), interval, callback)
+  })();
+  match scheduled{
+    Ok(id) => unsafe{kj(id)},
+    Err(error) => error.to_q()
+  }
+}
+
+/// `list` of [`timer_namespace`](fn.timer_namespace.html).
+extern "C" fn q_timers(_: K) -> K{
+  let timers=timers();
+  let ids=timers.iter().map(|timer| timer.id).collect::<Vec<_>>();
+  let intervals=timers.iter().map(|timer| timer.interval.unwrap_or_default().as_nanos() as J).collect::<Vec<_>>();
+  let remaining=timers.iter().map(|timer| timer.remaining.as_nanos() as J).collect::<Vec<_>>();
+  let table: Result<KOwned, KError>=q!("{[id; interval; remaining] ([] id; interval: `timespan$interval; remaining: `timespan$remaining)}", ids, intervals, remaining);
+  match table{
+    Ok(table) => table.into_raw(),
+    Err(error) => error.to_q()
+  }
+}
+
+/// `cancel` of [`timer_namespace`](fn.timer_namespace.html).
+extern "C" fn q_cancel_timer(id: K) -> K{
+  match id.get_long(){
+    Ok(id) => unsafe{kb(cancel_timer(id) as I)},
+    Err(error) => unsafe{krr(null_terminated_str_to_const_S(error))}
+  }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                              Utility                                 //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Convert `Duration` into `timespec`.
+fn to_timespec(duration: Duration) -> libc::timespec{
+  libc::timespec{
+    tv_sec: duration.as_secs() as libc::time_t,
+    tv_nsec: duration.subsec_nanos() as libc::c_long
+  }
+}
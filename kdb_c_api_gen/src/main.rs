@@ -0,0 +1,186 @@
+//! Generate a q script loading functions registered by `export!` in a shared library built with
+//!  `kdb_c_api`. The metadata of the functions (name, namespace, arity and description) is read from
+//!  the section embedded by `export!` and checked against the exported symbols of the library.
+//!
+//! # Usage
+//! ```bash
+//! tests]$ kdb-c-api-gen libc_api_examples.so -o libc_api_examples.q
+//! tests]$ q
+//! q)\l libc_api_examples.q
+//! q).capi.create_bool[]
+//! 1b
+//! ```
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Load Libraries                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+use kdb_c_api::export::{parse_metadata, Metadata, METADATA_SECTION};
+use object::{BinaryFormat, Object, ObjectSection};
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                          Global Variables                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+const USAGE: &str="usage: kdb-c-api-gen <shared library> [-o <output>] [-l <library name>]
+  -o <output>        Write the loader script to the file instead of stdout.
+  -l <library name>  Library name passed to `2:`. Defaults to the file name without extension.";
+
+/// Name of the section embedding metadata in PE format.
+const METADATA_SECTION_PE: &str=".kdbmeta";
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                               Structs                                //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Command line options.
+struct Options{
+  /// Path to the shared library.
+  path: String,
+  /// Path to the output file. Stdout is used if not specified.
+  output: Option<String>,
+  /// Library name passed to `2:`.
+  library: String
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                              Utility                                 //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Parse command line arguments.
+fn parse_options(args: &[String]) -> Result<Options, String>{
+  let mut path=None;
+  let mut output=None;
+  let mut library=None;
+  let mut args=args.iter();
+  while let Some(arg)=args.next(){
+    match arg.as_str(){
+      "-o" => output=Some(args.next().ok_or("missing output after -o")?.clone()),
+      "-l" => library=Some(args.next().ok_or("missing library name after -l")?.clone()),
+      "-h" | "--help" => return Err(USAGE.to_string()),
+      _ if path.is_none() => path=Some(arg.clone()),
+      _ => return Err(format!("unexpected argument: {}", arg))
+    }
+  }
+  let path=path.ok_or_else(|| USAGE.to_string())?;
+  let library=match library{
+    Some(library) => library,
+    None => Path::new(&path).file_stem().and_then(|stem| stem.to_str()).ok_or("invalid library path")?.to_string()
+  };
+  Ok(Options{path, output, library})
+}
+
+/// Read metadata records embedded by `export!` in a binary.
+fn read_metadata(file: &object::File) -> Result<Vec<Metadata>, String>{
+  let section_name=match file.format(){
+    BinaryFormat::Pe => METADATA_SECTION_PE,
+    _ => METADATA_SECTION
+  };
+  let section=file.section_by_name(section_name).ok_or("no function is registered by `export!`")?;
+  let data=section.data().map_err(|error| format!("failed to read metadata: {}", error))?;
+  Ok(parse_metadata(data)?)
+}
+
+/// Read metadata of the exported functions from a shared library. Functions whose symbols
+///  are not exported are reported to stderr and skipped.
+fn read_exports(bytes: &[u8]) -> Result<Vec<Metadata>, String>{
+  let file=object::File::parse(bytes).map_err(|error| format!("failed to parse shared library: {}", error))?;
+  let mut metadata=read_metadata(&file)?;
+  let symbols=file.exports().map_err(|error| format!("failed to read symbols: {}", error))?.iter().map(|symbol| {
+    let name=String::from_utf8_lossy(symbol.name());
+    // Mach-O prefixes C symbols with an underscore.
+    match file.format(){
+      BinaryFormat::MachO => name.trim_start_matches('_').to_string(),
+      _ => name.to_string()
+    }
+  }).collect::<HashSet<_>>();
+  metadata.retain(|function| {
+    let exported=symbols.contains(&function.name);
+    if !exported{
+      eprintln!("kdb-c-api-gen: `{}` is not exported. Add #[no_mangle]. Skipped.", function.name);
+    }
+    exported
+  });
+  metadata.sort_by_key(|function| function.qualified_name());
+  Ok(metadata)
+}
+
+/// Build a q script loading the functions.
+fn render(library: &str, functions: &[Metadata]) -> String{
+  let mut script=format!("/\n* @file {0}.q\n* @overview Load functions exported from `{0}`.\n*  Generated by kdb-c-api-gen. Do not edit.\n\\\n\n", library);
+  for function in functions{
+    for line in function.doc.lines(){
+      script.push_str(&format!("// {}\n", line));
+    }
+    script.push_str(&format!("{}: `{} 2: (`{}; {});\n", function.qualified_name(), library, function.name, function.arity));
+  }
+  script
+}
+
+fn run() -> Result<(), String>{
+  let args=env::args().skip(1).collect::<Vec<_>>();
+  let options=parse_options(&args)?;
+  let bytes=fs::read(&options.path).map_err(|error| format!("failed to read {}: {}", options.path, error))?;
+  let script=render(&options.library, &read_exports(&bytes)?);
+  match options.output{
+    Some(output) => fs::write(&output, script).map_err(|error| format!("failed to write {}: {}", output, error)),
+    None => {
+      print!("{}", script);
+      Ok(())
+    }
+  }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                                Main                                  //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+fn main(){
+  if let Err(error)=run(){
+    eprintln!("kdb-c-api-gen: {}", error);
+    process::exit(1);
+  }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                                Test                                  //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+#[cfg(test)]
+mod tests{
+  use super::*;
+  use kdb_c_api::*;
+
+  #[no_mangle]
+  pub extern "C" fn gen_round_trip(_: K, _: K, _: K) -> K{
+    KNULL!()
+  }
+  export!(".gen", gen_round_trip, 3, "Example of a round trip.\nSecond line.");
+
+  #[no_mangle]
+  pub extern "C" fn gen_root(_: K) -> K{
+    KNULL!()
+  }
+  export!(gen_root, 1);
+
+  /// Metadata embedded by `export!` in this test binary is read back through the linked section.
+  #[test]
+  fn round_trip(){
+    let bytes=fs::read(env::current_exe().unwrap()).unwrap();
+    let file=object::File::parse(&*bytes).unwrap();
+    let mut metadata=read_metadata(&file).unwrap();
+    metadata.sort_by_key(|function| function.qualified_name());
+    assert_eq!(metadata, vec![
+      Metadata{name: "gen_round_trip".to_string(), namespace: ".gen".to_string(), arity: 3, doc: "Example of a round trip.\nSecond line.".to_string()},
+      Metadata{name: "gen_root".to_string(), namespace: "".to_string(), arity: 1, doc: "".to_string()}
+    ]);
+    assert_eq!(render("libgen", &metadata), "/\n* @file libgen.q\n* @overview Load functions exported from `libgen`.\n*  Generated by kdb-c-api-gen. Do not edit.\n\\\n\n\
+      // Example of a round trip.\n// Second line.\n.gen.gen_round_trip: `libgen 2: (`gen_round_trip; 3);\n\
+      gen_root: `libgen 2: (`gen_root; 1);\n");
+  }
+}